@@ -0,0 +1,43 @@
+//! Builds an opening book for the standard Connect4 board, from the search or from the exact solver.
+//!
+//! cargo run --release --example opening_book -- <output file> [plies] [iterations per move, 0 for the solver] [seed]
+//!
+//! The solver is exact but slow in the first positions of a game, the search is fast but can be wrong. The search is seeded,
+//! so the same arguments always build the same book.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use connect4_mcts::clock::DefaultClock;
+use connect4_mcts::connect4::{Connect4, ThreatEvaluator};
+use connect4_mcts::engine::{Budget, Engine, MctsEngine};
+use connect4_mcts::mcts::SearchConfig;
+use connect4_mcts::opening_book::OpeningBook;
+use connect4_mcts::rollout::Rollout;
+use connect4_mcts::selection::Selection;
+use connect4_mcts::solver::Solver;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(output) = args.first() else {
+        eprintln!("usage: opening_book <output file> [plies] [iterations per move, 0 for the solver] [seed]");
+        std::process::exit(1);
+    };
+    let argument = |index: usize, default: usize| args.get(index).map_or(default, |arg| arg.parse().expect("arguments have to be numbers"));
+
+    let plies = argument(1, 4);
+    let iterations = argument(2, 100_000);
+    let seed = argument(3, 0) as u64;
+
+    let mut engine: Box<dyn Engine<Connect4<6,7,4>>> = if iterations == 0 {
+        Box::new(Solver::<6,7,4>::new(1 << 24))
+    }
+    else {
+        let config = SearchConfig::<Selection, Rollout, ThreatEvaluator>::default();
+        Box::new(MctsEngine::new(config, DefaultClock::new(), StdRng::seed_from_u64(seed)))
+    };
+
+    let book = OpeningBook::build(&Connect4::<6,7,4>::empty(), plies, engine.as_mut(), Budget::Nodes(iterations));
+    book.save(output).expect("cannot write the opening book");
+    eprintln!("{} positions written to {}", book.len(), output);
+}
@@ -0,0 +1,46 @@
+//! Plays Connect4 games of the search against itself and writes them as training data.
+//!
+//! cargo run --release --example self_play -- <output file> [games] [iterations per move] [opening moves] [temperature]
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use connect4_mcts::connect4::{Connect4, ThreatEvaluator};
+use connect4_mcts::mcts::SearchConfig;
+use connect4_mcts::rollout::Rollout;
+use connect4_mcts::selection::Selection;
+use connect4_mcts::self_play::{DataWriter, SelfPlay};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(output) = args.first() else {
+        eprintln!("usage: self_play <output file> [games] [iterations per move] [opening moves] [temperature]");
+        std::process::exit(1);
+    };
+    let argument = |index: usize, default: f32| args.get(index).map_or(default, |arg| arg.parse().expect("arguments have to be numbers"));
+
+    let games = argument(1, 100.) as usize;
+    let defaults = SelfPlay::default();
+    let self_play = SelfPlay {
+        iterations: argument(2, defaults.iterations as f32) as usize,
+        opening_moves: argument(3, defaults.opening_moves as f32) as usize,
+        temperature: argument(4, defaults.temperature),
+        ..defaults
+    };
+
+    let config = SearchConfig::<Selection, Rollout, ThreatEvaluator>::default();
+    let mut rng = StdRng::from_entropy();
+    let mut writer = DataWriter::new(BufWriter::new(File::create(output).expect("cannot create the output file"))).unwrap();
+
+    let mut results = [0usize; 3];
+    for game in 0..games {
+        let record = self_play.play(Connect4::<6,7,4>::empty(), &config, &mut rng);
+        writer.write(&record).expect("cannot write the game");
+
+        results[(record.result + 1) as usize] += 1;
+        eprintln!("game {}/{}: {} moves, X {} - O {} - draws {}", game + 1, games, record.positions.len(), results[2], results[0], results[1]);
+    }
+}
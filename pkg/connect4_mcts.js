@@ -1,82 +1,531 @@
+/* @ts-self-types="./connect4_mcts.d.ts" */
 
-let wasm;
-
-const heap = new Array(32).fill(undefined);
+/**
+ * Keeps the search tree between moves, so that the statistics gathered for the position actually played are reused.
+ * The search can also run in chunks with `start`, `step`, `peek_best_move` and `stop`, so that it does not block the page
+ * and can continue while the opponent thinks.
+ */
+export class Searcher {
+    static __wrap(ptr) {
+        const obj = Object.create(Searcher.prototype);
+        obj.__wbg_ptr = ptr;
+        SearcherFinalization.register(obj, obj.__wbg_ptr, obj);
+        return obj;
+    }
+    __destroy_into_raw() {
+        const ptr = this.__wbg_ptr;
+        this.__wbg_ptr = 0;
+        SearcherFinalization.unregister(this);
+        return ptr;
+    }
+    free() {
+        const ptr = this.__destroy_into_raw();
+        wasm.__wbg_searcher_free(ptr, 0);
+    }
+    /**
+     * @param {string} board
+     * @param {number} player
+     * @returns {Searcher | undefined}
+     */
+    static from_board(board, player) {
+        const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.searcher_from_board(ptr0, len0, player);
+        return ret === 0 ? undefined : Searcher.__wrap(ret);
+    }
+    /**
+     * @returns {boolean}
+     */
+    is_searching() {
+        const ret = wasm.searcher_is_searching(this.__wbg_ptr);
+        return ret !== 0;
+    }
+    /**
+     * iterations of the last `predict_best_move` which were not needed, because the best move was decided or proven earlier
+     * @returns {number}
+     */
+    iterations_saved() {
+        const ret = wasm.searcher_iterations_saved(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+     * bytes allocated by the search tree
+     * @returns {number}
+     */
+    memory_usage() {
+        const ret = wasm.searcher_memory_usage(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+     * the most visited move so far, the search is not changed
+     * @returns {number | undefined}
+     */
+    peek_best_move() {
+        const ret = wasm.searcher_peek_best_move(this.__wbg_ptr);
+        return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+    }
+    /**
+     * moves the root to the position after `column` was played, returns false if the move is not available
+     * @param {number} column
+     * @returns {boolean}
+     */
+    play(column) {
+        const ret = wasm.searcher_play(this.__wbg_ptr, column);
+        return ret !== 0;
+    }
+    /**
+     * @param {number} iters
+     * @returns {number | undefined}
+     */
+    predict_best_move(iters) {
+        const ret = wasm.searcher_predict_best_move(this.__wbg_ptr, iters);
+        return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+    }
+    /**
+     * @param {number} time_ms
+     * @returns {number | undefined}
+     */
+    predict_best_move_timed(time_ms) {
+        const ret = wasm.searcher_predict_best_move_timed(this.__wbg_ptr, time_ms);
+        return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+    }
+    /**
+     * `SearchReport` of the current root serialized as JSON, the iterations are those of the last search, or of all steps since `start`
+     * @returns {string}
+     */
+    report() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.searcher_report(this.__wbg_ptr);
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+    /**
+     * continues searching the current root while keeping the tree, e.g. to ponder on the position after `play`
+     */
+    resume() {
+        wasm.searcher_resume(this.__wbg_ptr);
+    }
+    /**
+     * sets the reward of a draw for the player to move, between -1 (as bad as a loss) and 1 (as good as a win), see `SearchConfig::draw_value`.
+     * Values outside of the range are clamped, NaN counts as 0
+     * @param {number} draw_value
+     */
+    set_draw_value(draw_value) {
+        wasm.searcher_set_draw_value(this.__wbg_ptr, draw_value);
+    }
+    /**
+     * stops `predict_best_move` once the most visited move leads by more visits than the remaining iterations can add
+     * if `visit_gap` is true, and once every move but one is proven to lose if `proven` is true
+     * @param {boolean} visit_gap
+     * @param {boolean} proven
+     */
+    set_early_stop(visit_gap, proven) {
+        wasm.searcher_set_early_stop(this.__wbg_ptr, visit_gap, proven);
+    }
+    /**
+     * stops rollouts after `cutoff` plies and values the position reached by `ThreatEvaluator`, blended with the evaluation
+     * of the leaf by `weight` between 0 and 1. `None` and 0 play rollouts to the end, 1 replaces them by the evaluation
+     * @param {number | null | undefined} cutoff
+     * @param {number} weight
+     */
+    set_leaf_evaluation(cutoff, weight) {
+        wasm.searcher_set_leaf_evaluation(this.__wbg_ptr, isLikeNone(cutoff) ? Number.MAX_SAFE_INTEGER : (cutoff) >>> 0, weight);
+    }
+    /**
+     * limits the number of nodes of the search tree, once it is reached the least visited subtrees are pruned
+     * if `prune` is true, otherwise the tree stops growing. 0 removes the limit
+     * @param {number} max_nodes
+     * @param {boolean} prune
+     */
+    set_max_nodes(max_nodes, prune) {
+        wasm.searcher_set_max_nodes(this.__wbg_ptr, max_nodes, prune);
+    }
+    /**
+     * takes one of "max-visits", "max-value", "robust-max", "secure-child", "sample", see `MoveSelection::from_name`,
+     * returns false if the name is not known or the temperature of "sample" is not positive and finite
+     * @param {string} name
+     * @param {number} parameter
+     * @returns {boolean}
+     */
+    set_move_selection(name, parameter) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.searcher_set_move_selection(this.__wbg_ptr, ptr0, len0, parameter);
+        return ret !== 0;
+    }
+    /**
+     * selects RAVE with the given schedule, see `RaveSchedule::from_name`, returns false if the name is not known
+     * @param {string} name
+     * @param {number} parameter
+     * @returns {boolean}
+     */
+    set_rave_schedule(name, parameter) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.searcher_set_rave_schedule(this.__wbg_ptr, ptr0, len0, parameter);
+        return ret !== 0;
+    }
+    /**
+     * takes one of "uniform", "tactical", "weighted", "softmax", returns false if the name is not known
+     * @param {string} name
+     * @returns {boolean}
+     */
+    set_rollout_policy(name) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.searcher_set_rollout_policy(this.__wbg_ptr, ptr0, len0);
+        return ret !== 0;
+    }
+    /**
+     * reseeds the random number generator, so that the following searches are reproducible
+     * @param {bigint} seed
+     */
+    set_seed(seed) {
+        wasm.searcher_set_seed(this.__wbg_ptr, seed);
+    }
+    /**
+     * takes one of "ucb1", "ucb1-tuned", "kl-ucb", "thompson", "rave", "puct", returns false if the name is not known
+     * @param {string} name
+     * @returns {boolean}
+     */
+    set_selection_policy(name) {
+        const ptr0 = passStringToWasm0(name, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.searcher_set_selection_policy(this.__wbg_ptr, ptr0, len0);
+        return ret !== 0;
+    }
+    /**
+     * sets the exploration constant of UCB1
+     * @param {number} constant
+     */
+    set_ucb1_constant(constant) {
+        wasm.searcher_set_ucb1_constant(this.__wbg_ptr, constant);
+    }
+    /**
+     * starts a new search of `board` with `player` to move, dropping the current tree but keeping the settings.
+     * Returns false if the board is not valid or it is not the turn of `player`, in which case nothing changes
+     * @param {string} board
+     * @param {number} player
+     * @returns {boolean}
+     */
+    start(board, player) {
+        const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.searcher_start(this.__wbg_ptr, ptr0, len0, player);
+        return ret !== 0;
+    }
+    /**
+     * runs up to `iters` more iterations of the search started by `start`, the early stop applies to these iterations only.
+     * Returns the number of iterations performed, 0 once the root is proven or the search was stopped
+     * @param {number} iters
+     * @returns {number}
+     */
+    step(iters) {
+        const ret = wasm.searcher_step(this.__wbg_ptr, iters);
+        return ret >>> 0;
+    }
+    /**
+     * ends the search and returns the move picked by the move selection, the tree is kept so that `play` can reuse it
+     * @returns {number | undefined}
+     */
+    stop() {
+        const ret = wasm.searcher_stop(this.__wbg_ptr);
+        return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+    }
+    /**
+     * number of iterations which went through the current root
+     * @returns {number}
+     */
+    visits() {
+        const ret = wasm.searcher_visits(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+}
+if (Symbol.dispose) Searcher.prototype[Symbol.dispose] = Searcher.prototype.free;
 
-heap.push(undefined, null, true, false);
+/**
+ * Searches the position and returns the `SearchReport` serialized as JSON
+ * @param {string} board
+ * @param {number} player
+ * @param {number} iters
+ * @returns {string | undefined}
+ */
+export function analyze(board, player, iters) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.analyze(ptr0, len0, player, iters);
+    let v2;
+    if (ret[0] !== 0) {
+        v2 = getStringFromWasm0(ret[0], ret[1]);
+        wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
+    }
+    return v2;
+}
 
-function getObject(idx) { return heap[idx]; }
+/**
+ * "ongoing", "draw", "x" or "o" for the winner, `None` if the board is invalid
+ * @param {string} board
+ * @returns {string | undefined}
+ */
+export function get_score(board) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.get_score(ptr0, len0);
+    let v2;
+    if (ret[0] !== 0) {
+        v2 = getStringFromWasm0(ret[0], ret[1]);
+        wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
+    }
+    return v2;
+}
 
-let heap_next = heap.length;
+/**
+ * plays the move of the opening book if the position is in it, searches otherwise. `None` if the board is not valid or it is
+ * not the turn of `player`, X moves first
+ * @param {string} board
+ * @param {number} player
+ * @param {number} iters
+ * @returns {number | undefined}
+ */
+export function predict_best_move(board, player, iters) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.predict_best_move(ptr0, len0, player, iters);
+    return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+}
 
-function dropObject(idx) {
-    if (idx < 36) return;
-    heap[idx] = heap_next;
-    heap_next = idx;
+/**
+ * Best move of the alpha-beta search with `ThreatEvaluator`, searching for `time_ms` milliseconds
+ * @param {string} board
+ * @param {number} player
+ * @param {number} time_ms
+ * @returns {number | undefined}
+ */
+export function predict_best_move_alpha_beta(board, player, time_ms) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.predict_best_move_alpha_beta(ptr0, len0, player, time_ms);
+    return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
 }
 
-function takeObject(idx) {
-    const ret = getObject(idx);
-    dropObject(idx);
-    return ret;
+/**
+ * Best move of the exact solver, the first one from the center if several are equally good. Positions with few tokens can
+ * take minutes to solve.
+ * @param {string} board
+ * @param {number} player
+ * @returns {number | undefined}
+ */
+export function predict_best_move_perfect(board, player) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.predict_best_move_perfect(ptr0, len0, player);
+    return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
 }
 
-function addHeapObject(obj) {
-    if (heap_next === heap.length) heap.push(heap.length + 1);
-    const idx = heap_next;
-    heap_next = heap[idx];
+/**
+ * Same as `predict_best_move`, but the search is reproducible for a given `seed`
+ * @param {string} board
+ * @param {number} player
+ * @param {number} iters
+ * @param {bigint} seed
+ * @returns {number | undefined}
+ */
+export function predict_best_move_seeded(board, player, iters, seed) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.predict_best_move_seeded(ptr0, len0, player, iters, seed);
+    return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+}
 
-    heap[idx] = obj;
-    return idx;
+/**
+ * @param {string} board
+ * @param {number} player
+ * @param {number} time_ms
+ * @returns {number | undefined}
+ */
+export function predict_best_move_timed(board, player, time_ms) {
+    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+    const len0 = WASM_VECTOR_LEN;
+    const ret = wasm.predict_best_move_timed(ptr0, len0, player, time_ms);
+    return ret === Number.MAX_SAFE_INTEGER ? undefined : ret;
+}
+function __wbg_get_imports() {
+    const import0 = {
+        __proto__: null,
+        __wbg___wbindgen_is_function_1f9d30630b8b1d3d: function(arg0) {
+            const ret = typeof(arg0) === 'function';
+            return ret;
+        },
+        __wbg___wbindgen_is_object_3c45d4f2dde4e749: function(arg0) {
+            const val = arg0;
+            const ret = typeof(val) === 'object' && val !== null;
+            return ret;
+        },
+        __wbg___wbindgen_is_string_90b56bc79aad6f6c: function(arg0) {
+            const ret = typeof(arg0) === 'string';
+            return ret;
+        },
+        __wbg___wbindgen_is_undefined_8865fb403f8fe9d8: function(arg0) {
+            const ret = arg0 === undefined;
+            return ret;
+        },
+        __wbg___wbindgen_throw_41e9ee4f547fc59a: function(arg0, arg1) {
+            throw new Error(getStringFromWasm0(arg0, arg1));
+        },
+        __wbg_call_187d372bd5fdd4aa: function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.call(arg1, arg2);
+            return ret;
+        }, arguments); },
+        __wbg_crypto_38df2bab126b63dc: function(arg0) {
+            const ret = arg0.crypto;
+            return ret;
+        },
+        __wbg_getRandomValues_c44a50d8cfdaebeb: function() { return handleError(function (arg0, arg1) {
+            arg0.getRandomValues(arg1);
+        }, arguments); },
+        __wbg_length_7f3c00c40364105e: function(arg0) {
+            const ret = arg0.length;
+            return ret;
+        },
+        __wbg_msCrypto_bd5a034af96bcba6: function(arg0) {
+            const ret = arg0.msCrypto;
+            return ret;
+        },
+        __wbg_new_with_length_3da0ad195f6f63ba: function(arg0) {
+            const ret = new Uint8Array(arg0 >>> 0);
+            return ret;
+        },
+        __wbg_node_84ea875411254db1: function(arg0) {
+            const ret = arg0.node;
+            return ret;
+        },
+        __wbg_now_dc2f476d93dbd3a9: function() {
+            const ret = Date.now();
+            return ret;
+        },
+        __wbg_process_44c7a14e11e9f69e: function(arg0) {
+            const ret = arg0.process;
+            return ret;
+        },
+        __wbg_prototypesetcall_bc27214492979395: function(arg0, arg1, arg2) {
+            Uint8Array.prototype.set.call(getArrayU8FromWasm0(arg0, arg1), arg2);
+        },
+        __wbg_randomFillSync_6c25eac9869eb53c: function() { return handleError(function (arg0, arg1) {
+            arg0.randomFillSync(arg1);
+        }, arguments); },
+        __wbg_require_b4edbdcf3e2a1ef0: function() { return handleError(function () {
+            const ret = module.require;
+            return ret;
+        }, arguments); },
+        __wbg_static_accessor_GLOBAL_266715b9d96ba635: function() {
+            const ret = typeof global === 'undefined' ? null : global;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_static_accessor_GLOBAL_THIS_10fb7dc1ae063179: function() {
+            const ret = typeof globalThis === 'undefined' ? null : globalThis;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_static_accessor_SELF_0b583911f537483a: function() {
+            const ret = typeof self === 'undefined' ? null : self;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_static_accessor_WINDOW_d7f903d1508cbdc4: function() {
+            const ret = typeof window === 'undefined' ? null : window;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_subarray_002b94d5e13d1411: function(arg0, arg1, arg2) {
+            const ret = arg0.subarray(arg1 >>> 0, arg2 >>> 0);
+            return ret;
+        },
+        __wbg_versions_276b2795b1c6a219: function(arg0) {
+            const ret = arg0.versions;
+            return ret;
+        },
+        __wbindgen_generic_0000000000000001: function(arg0, arg1) {
+            // Cast intrinsic for `Ref(Slice(U8)) -> NamedExternref("Uint8Array")`.
+            const ret = getArrayU8FromWasm0(arg0, arg1);
+            return ret;
+        },
+        __wbindgen_generic_0000000000000002: function(arg0, arg1) {
+            // Cast intrinsic for `Ref(String) -> Externref`.
+            const ret = getStringFromWasm0(arg0, arg1);
+            return ret;
+        },
+        __wbindgen_init_externref_table: function() {
+            const table = wasm.__wbindgen_externrefs;
+            const offset = table.grow(4);
+            table.set(0, undefined);
+            table.set(offset + 0, undefined);
+            table.set(offset + 1, null);
+            table.set(offset + 2, true);
+            table.set(offset + 3, false);
+        },
+    };
+    return {
+        __proto__: null,
+        "./connect4_mcts_bg.js": import0,
+    };
 }
 
-const cachedTextDecoder = new TextDecoder('utf-8', { ignoreBOM: true, fatal: true });
+const SearcherFinalization = (typeof FinalizationRegistry === 'undefined')
+    ? { register: () => {}, unregister: () => {} }
+    : new FinalizationRegistry(ptr => wasm.__wbg_searcher_free(ptr, 1));
 
-cachedTextDecoder.decode();
+function addToExternrefTable0(obj) {
+    const idx = wasm.__externref_table_alloc();
+    wasm.__wbindgen_externrefs.set(idx, obj);
+    return idx;
+}
 
-let cachedUint8Memory0;
-function getUint8Memory0() {
-    if (cachedUint8Memory0.byteLength === 0) {
-        cachedUint8Memory0 = new Uint8Array(wasm.memory.buffer);
-    }
-    return cachedUint8Memory0;
+function getArrayU8FromWasm0(ptr, len) {
+    ptr = ptr >>> 0;
+    return getUint8ArrayMemory0().subarray(ptr / 1, ptr / 1 + len);
 }
 
 function getStringFromWasm0(ptr, len) {
-    return cachedTextDecoder.decode(getUint8Memory0().subarray(ptr, ptr + len));
+    return decodeText(ptr >>> 0, len);
 }
 
-let WASM_VECTOR_LEN = 0;
+let cachedUint8ArrayMemory0 = null;
+function getUint8ArrayMemory0() {
+    if (cachedUint8ArrayMemory0 === null || cachedUint8ArrayMemory0.byteLength === 0) {
+        cachedUint8ArrayMemory0 = new Uint8Array(wasm.memory.buffer);
+    }
+    return cachedUint8ArrayMemory0;
+}
 
-const cachedTextEncoder = new TextEncoder('utf-8');
+function handleError(f, args) {
+    try {
+        return f.apply(this, args);
+    } catch (e) {
+        const idx = addToExternrefTable0(e);
+        wasm.__wbindgen_exn_store(idx);
+    }
+}
 
-const encodeString = (typeof cachedTextEncoder.encodeInto === 'function'
-    ? function (arg, view) {
-    return cachedTextEncoder.encodeInto(arg, view);
+function isLikeNone(x) {
+    return x === undefined || x === null;
 }
-    : function (arg, view) {
-    const buf = cachedTextEncoder.encode(arg);
-    view.set(buf);
-    return {
-        read: arg.length,
-        written: buf.length
-    };
-});
 
 function passStringToWasm0(arg, malloc, realloc) {
-
     if (realloc === undefined) {
         const buf = cachedTextEncoder.encode(arg);
-        const ptr = malloc(buf.length);
-        getUint8Memory0().subarray(ptr, ptr + buf.length).set(buf);
+        const ptr = malloc(buf.length, 1) >>> 0;
+        getUint8ArrayMemory0().subarray(ptr, ptr + buf.length).set(buf);
         WASM_VECTOR_LEN = buf.length;
         return ptr;
     }
 
     let len = arg.length;
-    let ptr = malloc(len);
+    let ptr = malloc(len, 1) >>> 0;
 
-    const mem = getUint8Memory0();
+    const mem = getUint8ArrayMemory0();
 
     let offset = 0;
 
@@ -85,259 +534,144 @@ function passStringToWasm0(arg, malloc, realloc) {
         if (code > 0x7F) break;
         mem[ptr + offset] = code;
     }
-
     if (offset !== len) {
         if (offset !== 0) {
             arg = arg.slice(offset);
         }
-        ptr = realloc(ptr, len, len = offset + arg.length * 3);
-        const view = getUint8Memory0().subarray(ptr + offset, ptr + len);
-        const ret = encodeString(arg, view);
+        ptr = realloc(ptr, len, len = offset + arg.length * 3, 1) >>> 0;
+        const view = getUint8ArrayMemory0().subarray(ptr + offset, ptr + len);
+        const ret = cachedTextEncoder.encodeInto(arg, view);
 
         offset += ret.written;
+        ptr = realloc(ptr, len, offset, 1) >>> 0;
     }
 
     WASM_VECTOR_LEN = offset;
     return ptr;
 }
 
-let cachedInt32Memory0;
-function getInt32Memory0() {
-    if (cachedInt32Memory0.byteLength === 0) {
-        cachedInt32Memory0 = new Int32Array(wasm.memory.buffer);
-    }
-    return cachedInt32Memory0;
-}
-/**
-* @param {string} board
-* @param {number} player
-* @param {number} iters
-* @returns {number | undefined}
-*/
-export function predict_best_move(board, player, iters) {
-    try {
-        const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
-        const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
-        const len0 = WASM_VECTOR_LEN;
-        wasm.predict_best_move(retptr, ptr0, len0, player, iters);
-        var r0 = getInt32Memory0()[retptr / 4 + 0];
-        var r1 = getInt32Memory0()[retptr / 4 + 1];
-        return r0 === 0 ? undefined : r1 >>> 0;
-    } finally {
-        wasm.__wbindgen_add_to_stack_pointer(16);
+let cachedTextDecoder = new TextDecoder('utf-8', { ignoreBOM: true, fatal: true });
+cachedTextDecoder.decode();
+const MAX_SAFARI_DECODE_BYTES = 2146435072;
+let numBytesDecoded = 0;
+function decodeText(ptr, len) {
+    numBytesDecoded += len;
+    if (numBytesDecoded >= MAX_SAFARI_DECODE_BYTES) {
+        cachedTextDecoder = new TextDecoder('utf-8', { ignoreBOM: true, fatal: true });
+        cachedTextDecoder.decode();
+        numBytesDecoded = len;
     }
+    return cachedTextDecoder.decode(getUint8ArrayMemory0().subarray(ptr, ptr + len));
 }
 
-/**
-* @param {string} board
-* @returns {number | undefined}
-*/
-export function get_score(board) {
-    const ptr0 = passStringToWasm0(board, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
-    const len0 = WASM_VECTOR_LEN;
-    const ret = wasm.get_score(ptr0, len0);
-    return ret === 0xFFFFFF ? undefined : ret;
-}
+const cachedTextEncoder = new TextEncoder();
 
-function handleError(f, args) {
-    try {
-        return f.apply(this, args);
-    } catch (e) {
-        wasm.__wbindgen_exn_store(addHeapObject(e));
-    }
+if (!('encodeInto' in cachedTextEncoder)) {
+    cachedTextEncoder.encodeInto = function (arg, view) {
+        const buf = cachedTextEncoder.encode(arg);
+        view.set(buf);
+        return {
+            read: arg.length,
+            written: buf.length
+        };
+    };
 }
 
-function getArrayU8FromWasm0(ptr, len) {
-    return getUint8Memory0().subarray(ptr / 1, ptr / 1 + len);
+let WASM_VECTOR_LEN = 0;
+
+let wasmModule, wasmInstance, wasm;
+function __wbg_finalize_init(instance, module) {
+    wasmInstance = instance;
+    wasm = instance.exports;
+    wasmModule = module;
+    cachedUint8ArrayMemory0 = null;
+    wasm.__wbindgen_start();
+    return wasm;
 }
 
-async function load(module, imports) {
+async function __wbg_load(module, imports) {
     if (typeof Response === 'function' && module instanceof Response) {
+        if (!module.ok) {
+            throw new Error(`failed to fetch Wasm: ${module.status} ${module.statusText} fetching '${module.url}'`);
+        }
+
         if (typeof WebAssembly.instantiateStreaming === 'function') {
             try {
                 return await WebAssembly.instantiateStreaming(module, imports);
-
             } catch (e) {
-                if (module.headers.get('Content-Type') != 'application/wasm') {
-                    console.warn("`WebAssembly.instantiateStreaming` failed because your server does not serve wasm with `application/wasm` MIME type. Falling back to `WebAssembly.instantiate` which is slower. Original error:\n", e);
+                const validResponse = expectedResponseType(module.type);
 
-                } else {
-                    throw e;
-                }
+                if (validResponse && module.headers.get('Content-Type') !== 'application/wasm') {
+                    console.warn("`WebAssembly.instantiateStreaming` failed because your server does not serve Wasm with `application/wasm` MIME type. Falling back to `WebAssembly.instantiate` which is slower. Original error:\n", e);
+
+                } else { throw e; }
             }
         }
 
         const bytes = await module.arrayBuffer();
         return await WebAssembly.instantiate(bytes, imports);
-
     } else {
         const instance = await WebAssembly.instantiate(module, imports);
 
         if (instance instanceof WebAssembly.Instance) {
             return { instance, module };
-
         } else {
             return instance;
         }
     }
-}
 
-function getImports() {
-    const imports = {};
-    imports.wbg = {};
-    imports.wbg.__wbg_randomFillSync_91e2b39becca6147 = function() { return handleError(function (arg0, arg1, arg2) {
-        getObject(arg0).randomFillSync(getArrayU8FromWasm0(arg1, arg2));
-    }, arguments) };
-    imports.wbg.__wbindgen_object_drop_ref = function(arg0) {
-        takeObject(arg0);
-    };
-    imports.wbg.__wbg_getRandomValues_b14734aa289bc356 = function() { return handleError(function (arg0, arg1) {
-        getObject(arg0).getRandomValues(getObject(arg1));
-    }, arguments) };
-    imports.wbg.__wbg_process_e56fd54cf6319b6c = function(arg0) {
-        const ret = getObject(arg0).process;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbindgen_is_object = function(arg0) {
-        const val = getObject(arg0);
-        const ret = typeof(val) === 'object' && val !== null;
-        return ret;
-    };
-    imports.wbg.__wbg_versions_77e21455908dad33 = function(arg0) {
-        const ret = getObject(arg0).versions;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_node_0dd25d832e4785d5 = function(arg0) {
-        const ret = getObject(arg0).node;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbindgen_is_string = function(arg0) {
-        const ret = typeof(getObject(arg0)) === 'string';
-        return ret;
-    };
-    imports.wbg.__wbg_static_accessor_NODE_MODULE_26b231378c1be7dd = function() {
-        const ret = module;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_require_0db1598d9ccecb30 = function() { return handleError(function (arg0, arg1, arg2) {
-        const ret = getObject(arg0).require(getStringFromWasm0(arg1, arg2));
-        return addHeapObject(ret);
-    }, arguments) };
-    imports.wbg.__wbg_crypto_b95d7173266618a9 = function(arg0) {
-        const ret = getObject(arg0).crypto;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_msCrypto_5a86d77a66230f81 = function(arg0) {
-        const ret = getObject(arg0).msCrypto;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_newnoargs_fc5356289219b93b = function(arg0, arg1) {
-        const ret = new Function(getStringFromWasm0(arg0, arg1));
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_call_4573f605ca4b5f10 = function() { return handleError(function (arg0, arg1) {
-        const ret = getObject(arg0).call(getObject(arg1));
-        return addHeapObject(ret);
-    }, arguments) };
-    imports.wbg.__wbindgen_object_clone_ref = function(arg0) {
-        const ret = getObject(arg0);
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_self_ba1ddafe9ea7a3a2 = function() { return handleError(function () {
-        const ret = self.self;
-        return addHeapObject(ret);
-    }, arguments) };
-    imports.wbg.__wbg_window_be3cc430364fd32c = function() { return handleError(function () {
-        const ret = window.window;
-        return addHeapObject(ret);
-    }, arguments) };
-    imports.wbg.__wbg_globalThis_56d9c9f814daeeee = function() { return handleError(function () {
-        const ret = globalThis.globalThis;
-        return addHeapObject(ret);
-    }, arguments) };
-    imports.wbg.__wbg_global_8c35aeee4ac77f2b = function() { return handleError(function () {
-        const ret = global.global;
-        return addHeapObject(ret);
-    }, arguments) };
-    imports.wbg.__wbindgen_is_undefined = function(arg0) {
-        const ret = getObject(arg0) === undefined;
-        return ret;
-    };
-    imports.wbg.__wbg_buffer_de1150f91b23aa89 = function(arg0) {
-        const ret = getObject(arg0).buffer;
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_new_97cf52648830a70d = function(arg0) {
-        const ret = new Uint8Array(getObject(arg0));
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_set_a0172b213e2469e9 = function(arg0, arg1, arg2) {
-        getObject(arg0).set(getObject(arg1), arg2 >>> 0);
-    };
-    imports.wbg.__wbg_length_e09c0b925ab8de5d = function(arg0) {
-        const ret = getObject(arg0).length;
-        return ret;
-    };
-    imports.wbg.__wbg_newwithlength_e833b89f9db02732 = function(arg0) {
-        const ret = new Uint8Array(arg0 >>> 0);
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbg_subarray_9482ae5cd5cd99d3 = function(arg0, arg1, arg2) {
-        const ret = getObject(arg0).subarray(arg1 >>> 0, arg2 >>> 0);
-        return addHeapObject(ret);
-    };
-    imports.wbg.__wbindgen_throw = function(arg0, arg1) {
-        throw new Error(getStringFromWasm0(arg0, arg1));
-    };
-    imports.wbg.__wbindgen_memory = function() {
-        const ret = wasm.memory;
-        return addHeapObject(ret);
-    };
-
-    return imports;
+    function expectedResponseType(type) {
+        switch (type) {
+            case 'basic': case 'cors': case 'default': return true;
+        }
+        return false;
+    }
 }
 
-function initMemory(imports, maybe_memory) {
-
-}
+function initSync(module) {
+    if (wasm !== undefined) return wasm;
 
-function finalizeInit(instance, module) {
-    wasm = instance.exports;
-    init.__wbindgen_wasm_module = module;
-    cachedInt32Memory0 = new Int32Array(wasm.memory.buffer);
-    cachedUint8Memory0 = new Uint8Array(wasm.memory.buffer);
 
+    if (module !== undefined) {
+        if (Object.getPrototypeOf(module) === Object.prototype) {
+            ({module} = module)
+        } else {
+            console.warn('using deprecated parameters for `initSync()`; pass a single object instead')
+        }
+    }
 
-    return wasm;
+    const imports = __wbg_get_imports();
+    if (!(module instanceof WebAssembly.Module)) {
+        module = new WebAssembly.Module(module);
+    }
+    const instance = new WebAssembly.Instance(module, imports);
+    return __wbg_finalize_init(instance, module);
 }
 
-function initSync(bytes) {
-    const imports = getImports();
-
-    initMemory(imports);
-
-    const module = new WebAssembly.Module(bytes);
-    const instance = new WebAssembly.Instance(module, imports);
+async function __wbg_init(module_or_path) {
+    if (wasm !== undefined) return wasm;
 
-    return finalizeInit(instance, module);
-}
 
-async function init(input) {
-    if (typeof input === 'undefined') {
-        input = new URL('connect4_mcts_bg.wasm', import.meta.url);
+    if (module_or_path !== undefined) {
+        if (Object.getPrototypeOf(module_or_path) === Object.prototype) {
+            ({module_or_path} = module_or_path)
+        } else {
+            console.warn('using deprecated parameters for the initialization function; pass a single object instead')
+        }
     }
-    const imports = getImports();
 
-    if (typeof input === 'string' || (typeof Request === 'function' && input instanceof Request) || (typeof URL === 'function' && input instanceof URL)) {
-        input = fetch(input);
+    if (module_or_path === undefined) {
+        module_or_path = new URL('connect4_mcts_bg.wasm', import.meta.url);
     }
+    const imports = __wbg_get_imports();
 
-    initMemory(imports);
+    if (typeof module_or_path === 'string' || (typeof Request === 'function' && module_or_path instanceof Request) || (typeof URL === 'function' && module_or_path instanceof URL)) {
+        module_or_path = fetch(module_or_path);
+    }
 
-    const { instance, module } = await load(await input, imports);
+    const { instance, module } = await __wbg_load(await module_or_path, imports);
 
-    return finalizeInit(instance, module);
+    return __wbg_finalize_init(instance, module);
 }
 
-export { initSync }
-export default init;
+export { initSync, __wbg_init as default };
@@ -0,0 +1,359 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::clock::Clock;
+use super::engine::{Budget, Engine};
+use super::evaluation::Evaluator;
+use super::mcts::{GeneralGame, Outcome};
+
+/// value of a won position, minus the number of plies to the win. Evaluations are between -1 and 1
+const WIN: f32 = 1000.;
+/// values further than this from 0 are wins or losses
+const PROVEN: f32 = WIN / 2.;
+/// nodes between two readings of the clock
+const CLOCK_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    /// the value is at least this, the search failed high
+    Lower,
+    /// the value is at most this, the search failed low
+    Upper
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    key: u64,
+    /// `usize::MAX` if no position below was valued by the evaluator, so that the value holds for any depth
+    depth: usize,
+    /// wins and losses are counted from this node, not from the root
+    value: f32,
+    bound: Bound,
+    /// position of the best move in `GeneralGame::get_available`, so that the table does not depend on the type of the moves
+    best_move: usize
+}
+
+/// Outcome of `AlphaBeta::search`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlphaBetaResult<M> {
+    /// `None` if the game is over
+    pub best_move: Option<M>,
+    /// value for the player to move, `WIN - plies` for a forced win in `plies` plies and between -1 and 1 otherwise
+    pub value: f32,
+    /// depth of the deepest completed iteration
+    pub depth: usize,
+    pub nodes: usize
+}
+
+impl<M> AlphaBetaResult<M> {
+    /// plies to the end of the game if the value is a forced win (positive) or loss (negative)
+    pub fn plies_to_end(&self) -> Option<i32> {
+        if self.value.abs() < PROVEN {
+            return None;
+        }
+
+        return Some(((WIN - self.value.abs()).round() as i32) * self.value.signum() as i32);
+    }
+}
+
+/// Negamax with alpha-beta pruning and iterative deepening. Moves are ordered by the best move stored in the transposition
+/// table and then by `GeneralGame::move_heuristic`, positions at the horizon are valued by `evaluator`.
+pub struct AlphaBeta<E, C: Clock> {
+    pub evaluator: E,
+    pub clock: C,
+    /// deepest iteration, the search also stops once every line reaches the end of the game
+    pub max_depth: usize,
+    table: Vec<Option<Entry>>,
+    nodes: usize,
+    node_limit: usize,
+    deadline: f64,
+    aborted: bool,
+    /// whether some position was valued by the evaluator in the current iteration
+    horizon: bool,
+    /// position of the best move at the root, as in `Entry`
+    root_move: Option<usize>
+}
+
+impl<E, C: Clock> AlphaBeta<E, C> {
+    /// `table_size` is the number of entries of the transposition table, rounded up to a power of two
+    pub fn new(evaluator: E, clock: C, table_size: usize) -> AlphaBeta<E, C> {
+        return AlphaBeta {
+            evaluator, clock, max_depth: 64,
+            table: vec![None; table_size.max(1).next_power_of_two()],
+            nodes: 0, node_limit: usize::MAX, deadline: f64::INFINITY, aborted: false, horizon: false, root_move: None
+        };
+    }
+
+    /// empties the transposition table
+    pub fn clear(&mut self) {
+        self.table.fill(None);
+    }
+
+    fn probe(&self, key: u64) -> Option<Entry> {
+        return self.table[key as usize & (self.table.len() - 1)].filter(|entry| entry.key == key);
+    }
+
+    fn store(&mut self, entry: Entry) {
+        let index = entry.key as usize & (self.table.len() - 1);
+        self.table[index] = Some(entry);
+    }
+
+    /// whether the budget ran out, only checked after the first iteration so that there is always a move
+    fn out_of_budget(&mut self) -> bool {
+        if self.nodes > self.node_limit {
+            return true;
+        }
+        return self.nodes.is_multiple_of(CLOCK_INTERVAL) && self.clock.now_ms() > self.deadline;
+    }
+}
+
+fn table_key<T: Hash>(game: &T, player: i8) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    game.hash(&mut hasher);
+    player.hash(&mut hasher);
+    return hasher.finish();
+}
+
+/// wins and losses are stored counted from the node, so that they stay correct when the node is reached at another ply
+fn to_table(value: f32, ply: usize) -> f32 {
+    if value > PROVEN { return value + ply as f32; }
+    if value < -PROVEN { return value - ply as f32; }
+    return value;
+}
+
+fn from_table(value: f32, ply: usize) -> f32 {
+    if value > PROVEN { return value - ply as f32; }
+    if value < -PROVEN { return value + ply as f32; }
+    return value;
+}
+
+impl<E, C: Clock> AlphaBeta<E, C> {
+    /// Searches with iterative deepening until `budget` runs out, `max_depth` is reached or the value is known exactly.
+    /// The result of the last completed iteration is returned, the first iteration is always completed.
+    pub fn search<T: GeneralGame + Hash>(&mut self, game: &T, budget: Budget) -> AlphaBetaResult<T::Move> where E: Evaluator<T> {
+        self.nodes = 0;
+        self.aborted = false;
+        let (node_limit, deadline) = match budget {
+            Budget::Nodes(nodes) => (nodes, f64::INFINITY),
+            Budget::Milliseconds(budget_ms) => (usize::MAX, self.clock.now_ms() + budget_ms)
+        };
+
+        // moves are played and taken back on a single copy
+        let mut game = game.clone();
+        let player = game.player();
+        let mut result = AlphaBetaResult { best_move: None, value: 0., depth: 0, nodes: 0 };
+        for depth in 1..=self.max_depth {
+            // the first iteration may not be interrupted
+            (self.node_limit, self.deadline) = if depth == 1 { (usize::MAX, f64::INFINITY) } else { (node_limit, deadline) };
+            self.horizon = false;
+            self.root_move = None;
+
+            let value = self.negamax(&mut game, player, depth, -f32::INFINITY, f32::INFINITY, 0);
+            if self.aborted {
+                break;
+            }
+
+            result = AlphaBetaResult { best_move: self.root_move.map(|position| game.get_available()[position]), value, depth, nodes: self.nodes };
+            if ! self.horizon || value.abs() > PROVEN {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+
+        return result;
+    }
+
+    fn negamax<T: GeneralGame + Hash>(&mut self, game: &mut T, player: i8, depth: usize, mut alpha: f32, beta: f32, ply: usize) -> f32 where E: Evaluator<T> {
+        self.nodes += 1;
+        if self.out_of_budget() {
+            self.aborted = true;
+            return 0.;
+        }
+
+        match game.get_outcome() {
+            Outcome::Win(winner) => return (winner * player) as f32 * (WIN - ply as f32),
+            Outcome::Draw => return 0.,
+            Outcome::Ongoing => ()
+        }
+        let moves = game.get_available();
+        if depth == 0 {
+            self.horizon = true;
+            return self.evaluator.evaluate(game, player);
+        }
+
+        let key = table_key(game, player);
+        let entry = self.probe(key);
+        if let Some(entry) = entry {
+            let value = from_table(entry.value, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => value >= beta,
+                Bound::Upper => value <= alpha
+            };
+            if ply > 0 && entry.depth >= depth && cutoff {
+                self.horizon |= entry.depth != usize::MAX;
+                return value;
+            }
+        }
+
+        // the best move of an earlier search first, then the most promising moves
+        let best_known = entry.map(|entry| entry.best_move);
+        let mut heuristics = Vec::from_iter(moves.iter().enumerate().map(|(position, &index)| (game.move_heuristic(index, player), position)));
+        heuristics.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut order = Vec::from_iter(heuristics.into_iter().map(|(_, position)| position));
+        if let Some(first) = order.iter().position(|&position| Some(position) == best_known) {
+            order[..=first].rotate_right(1);
+        }
+
+        let original_alpha = alpha;
+        let outer_horizon = std::mem::replace(&mut self.horizon, false);
+        let mut best_value = -f32::INFINITY;
+        let mut best_move = order[0];
+        for position in order {
+            game.update(moves[position]);
+            let value = -self.negamax(game, -player, depth - 1, -beta, -alpha, ply + 1);
+            game.undo();
+            if self.aborted {
+                return 0.;
+            }
+
+            if value > best_value {
+                best_value = value;
+                best_move = position;
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_value <= original_alpha { Bound::Upper } else if best_value >= beta { Bound::Lower } else { Bound::Exact };
+        let stored_depth = if self.horizon { depth } else { usize::MAX };
+        self.store(Entry { key, depth: stored_depth, value: to_table(best_value, ply), bound, best_move });
+        self.horizon |= outer_horizon;
+        if ply == 0 {
+            self.root_move = Some(best_move);
+        }
+
+        return best_value;
+    }
+}
+
+impl<T: GeneralGame + Hash, E: Evaluator<T>, C: Clock> Engine<T> for AlphaBeta<E, C> {
+    fn choose_move(&mut self, game: &T, budget: Budget) -> Option<T::Move> {
+        return self.search(game, budget).best_move;
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::connect4::{Connect4, ThreatEvaluator};
+#[cfg(test)]
+use super::clock::FakeClock;
+#[cfg(test)]
+use super::engine::{play, MctsEngine};
+#[cfg(test)]
+use super::evaluation::NoEvaluator;
+#[cfg(test)]
+use super::mcts::SearchConfig;
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::Ucb1;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+#[cfg(test)]
+fn alpha_beta<E>(evaluator: E) -> AlphaBeta<E, FakeClock> {
+    return AlphaBeta::new(evaluator, FakeClock { time: 0., step: 1. }, 1 << 16);
+}
+
+#[test]
+fn test_alpha_beta_tictactoe(){
+    let mut engine = alpha_beta(NoEvaluator);
+
+    // the empty board is a draw and the whole tree is searched
+    let result = engine.search(&TicTacToe::from_string("...\n...\n...").unwrap(), Budget::Nodes(usize::MAX));
+    assert_eq!(result.value, 0.);
+    assert!(result.depth <= 9);
+    assert_eq!(result.plies_to_end(), None);
+
+    // X wins right away, O loses after X completes a line
+    let game = TicTacToe::from_string("XX.\nOO.\n...").unwrap();
+    let result = engine.search(&game, Budget::Nodes(usize::MAX));
+    assert_eq!((result.best_move, result.plies_to_end()), (Some(Square(0,2)), Some(1)));
+    let game = TicTacToe::from_string("XX.\nOO.\nX..").unwrap();
+    let result = engine.search(&game, Budget::Nodes(usize::MAX));
+    assert_eq!((result.best_move, result.plies_to_end()), (Some(Square(1,2)), Some(1)));
+
+    // O blocks, but X has a fork
+    let game = TicTacToe::from_string("X..\n.O.\n..X").unwrap();
+    let result = engine.search(&game, Budget::Nodes(usize::MAX));
+    assert_eq!(result.value, 0.);
+    assert!([Square(0,1), Square(1,0), Square(1,2), Square(2,1)].contains(&result.best_move.unwrap()));
+
+    assert_eq!(engine.search(&TicTacToe::from_string("XXX\nOO.\n...").unwrap(), Budget::Nodes(100)).best_move, None);
+}
+
+#[test]
+fn test_alpha_beta_connect4(){
+    let mut engine = alpha_beta(ThreatEvaluator::default());
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .OO....\n\
+                        .XX....\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+
+    // X makes an open three and wins on the next move
+    let result = engine.search(&game, Budget::Nodes(20_000));
+    assert!([0, 3].contains(&result.best_move.unwrap()));
+    assert_eq!(result.plies_to_end(), Some(3));
+
+    // O has to block one side of the two, anything else loses
+    let game = Connect4::<6,7,4>::from_string(&str.replace(".OO....", "..O....")).unwrap();
+    let result = engine.search(&game, Budget::Nodes(20_000));
+    assert!([0, 3].contains(&result.best_move.unwrap()));
+    assert!(result.value > -PROVEN);
+}
+
+#[test]
+fn test_alpha_beta_budget(){
+    let game = Connect4::<6,7,4>::empty();
+
+    // the first iteration is always completed, later ones stop when the budget runs out
+    let mut engine = alpha_beta(ThreatEvaluator::default());
+    let result = engine.search(&game, Budget::Nodes(1));
+    assert_eq!(result.depth, 1);
+    assert!(result.best_move.is_some());
+
+    let result = engine.search(&game, Budget::Nodes(5000));
+    assert!(result.depth > 2 && result.nodes <= 5001);
+
+    // the fake clock advances once every CLOCK_INTERVAL nodes
+    engine.clear();
+    let result = engine.search(&game, Budget::Milliseconds(20.));
+    assert!(result.depth > 2 && result.nodes <= 21 * CLOCK_INTERVAL);
+
+    engine.max_depth = 2;
+    assert_eq!(engine.search(&game, Budget::Nodes(usize::MAX)).depth, 2);
+}
+
+#[test]
+fn test_alpha_beta_against_mcts(){
+    let game = TicTacToe::from_string("...\n...\n...").unwrap();
+    let mut engine = alpha_beta(NoEvaluator);
+    let mut mcts = MctsEngine::new(SearchConfig::<Ucb1, UniformRollout>::default(), FakeClock { time: 0., step: 1. }, StdRng::seed_from_u64(0));
+
+    // the complete search never loses
+    for budget in [Budget::Nodes(50), Budget::Nodes(5000)] {
+        assert_ne!(play(game.clone(), &mut engine, &mut mcts, budget), Outcome::Win(-1));
+        assert_ne!(play(game.clone(), &mut mcts, &mut engine, budget), Outcome::Win(1));
+    }
+}
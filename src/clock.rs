@@ -0,0 +1,98 @@
+/// Source of time for the time-budgeted search, so that it can run both natively and in the browser
+pub trait Clock {
+    /// returns the number of milliseconds elapsed since some fixed point in time
+    fn now_ms(&mut self) -> f64;
+}
+
+/// Clock backed by `std::time::Instant`, which is not available in wasm
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SystemClock {
+    start: std::time::Instant
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        return SystemClock { start: std::time::Instant::now() };
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        return SystemClock::new();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for SystemClock {
+    fn now_ms(&mut self) -> f64 {
+        return self.start.elapsed().as_secs_f64() * 1000.;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod js {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = Date)]
+        pub fn now() -> f64;
+    }
+}
+
+/// Clock backed by javascript's `Date.now()`
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub struct JsClock;
+
+#[cfg(target_arch = "wasm32")]
+impl JsClock {
+    pub fn new() -> JsClock {
+        return JsClock;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Clock for JsClock {
+    fn now_ms(&mut self) -> f64 {
+        return js::now();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type DefaultClock = SystemClock;
+#[cfg(target_arch = "wasm32")]
+pub type DefaultClock = JsClock;
+
+/// Clock which advances by a fixed step every time it is read, used to make timed searches deterministic in tests
+#[cfg(test)]
+pub struct FakeClock {
+    pub time: f64,
+    pub step: f64
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now_ms(&mut self) -> f64 {
+        self.time += self.step;
+        return self.time;
+    }
+}
+
+#[test]
+fn test_fake_clock(){
+    let mut clock = FakeClock { time: 0., step: 2.5 };
+    assert_eq!(clock.now_ms(), 2.5);
+    assert_eq!(clock.now_ms(), 5.);
+}
+
+#[test]
+fn test_system_clock(){
+    let mut clock = SystemClock::new();
+    let first = clock.now_ms();
+    let second = clock.now_ms();
+    assert!(first >= 0.);
+    assert!(second >= first);
+}
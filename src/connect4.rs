@@ -1,19 +1,52 @@
 use std::{fmt};
-use super::mcts::GeneralGame;
+use std::hash::{Hash, Hasher};
+use super::evaluation::Evaluator;
+use super::mcts::{GeneralGame, Outcome};
+use super::network::Encode;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Connect4<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize>{
-    pub board: [[i8;COLUMNS];ROWS] // (0,0) is in the top-left corner, indexing is (row,column)
+    pub board: [[i8;COLUMNS];ROWS], // (0,0) is in the top-left corner, indexing is (row,column)
+    player: i8, // X (1) starts, so the player to move follows from the numbers of tokens
+    // the moves played since the position was created as a chain through the board, so that cloning stays a copy
+    last: u8, // column of the last move plus 1, 0 if no move was played
+    previous: [[u8;COLUMNS];ROWS] // `last` before the token in the cell was played
+}
+
+/// positions are equal regardless of the moves which led to them
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> PartialEq for Connect4<ROWS,COLUMNS,CONNECT> {
+    fn eq(&self, other: &Self) -> bool {
+        return self.board == other.board && self.player == other.player;
+    }
+}
+
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> Eq for Connect4<ROWS,COLUMNS,CONNECT> {}
+
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> Hash for Connect4<ROWS,COLUMNS,CONNECT> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.player.hash(state);
+    }
 }
 
 impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> Connect4<ROWS,COLUMNS,CONNECT> {    
     pub fn empty() -> Connect4<ROWS,COLUMNS,CONNECT> {
-        return Connect4 {board:[[0;COLUMNS];ROWS]};
+        return Connect4 {board:[[0;COLUMNS];ROWS], player: 1, last: 0, previous: [[0;COLUMNS];ROWS]};
     }
 
     /// takes in a string where each line contains 'X', 'O', '.' and ends with '\n' or '\r'
     pub fn from_string(val : &str) -> Option<Connect4<ROWS,COLUMNS,CONNECT>> {
-        let mut connect4 = Connect4::<ROWS,COLUMNS,CONNECT>::empty();
+        return Self::parse(val).and_then(Self::from_board);
+    }
+
+    /// parses boards which cannot be reached by taking turns, to test the lines on the board, X is to move
+    #[cfg(test)]
+    pub(crate) fn from_string_unchecked(val : &str) -> Option<Connect4<ROWS,COLUMNS,CONNECT>> {
+        return Self::parse(val).map(|board| Connect4 {board, player: 1, last: 0, previous: [[0;COLUMNS];ROWS]});
+    }
+
+    fn parse(val : &str) -> Option<[[i8;COLUMNS];ROWS]> {
+        let mut board = [[0;COLUMNS];ROWS];
 
         let mut row = 0usize;
         let mut column = 0usize;
@@ -36,41 +69,88 @@ impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> Connect4<ROWS,COL
                 'O' => -1,
                 _ => return None
             };
-            connect4.board[row][column] = val;
+            board[row][column] = val;
 
             column += 1;
         }
-        // check for gaps
-        for col in 0..COLUMNS{
-            let mut gap = false;
-            for row in (0..ROWS).rev() {
-                if connect4.board[row][col] == 0 {
-                    gap = true;
-                }
-                if connect4.board[row][col] != 0 && gap {
-                    return None;
-                }
-            }
+
+        return Some(board);
+    }
+
+    /// `None` if some token floats above an empty cell, or if the numbers of tokens cannot be reached by taking turns
+    pub fn from_board(board: [[i8;COLUMNS];ROWS]) -> Option<Connect4<ROWS,COLUMNS,CONNECT>> {
+        // check for gaps, a token right above an empty cell
+        if board.windows(2).any(|rows| (0..COLUMNS).any(|col| rows[0][col] != 0 && rows[1][col] == 0)) {
+            return None;
         }
 
-        return Some(connect4)
+        let tokens: i32 = board.iter().flatten().map(|&cell| cell as i32).sum();
+        let player = match tokens {
+            0 => 1,
+            1 => -1,
+            _ => return None
+        };
+
+        return Some(Connect4 {board, player, last: 0, previous: [[0;COLUMNS];ROWS]});
     }
-}
 
-impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> fmt::Display for Connect4<ROWS,COLUMNS,CONNECT> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in 0..ROWS{
-            for col in 0..COLUMNS{
-                write!(f, "{} ", if self.board[row][col] == 1 {'X'} else if self.board[row][col] == -1 {'O'} else {'.'}).unwrap();
+    /// Plays `column` for `player`. Returns false without changing anything if it is not the turn of `player`, the column
+    /// is full or the game is over.
+    pub fn play(&mut self, column: usize, player: i8) -> bool {
+        if player != self.player || column >= COLUMNS || self.board[0][column] != 0 || self.get_outcome().is_over() {
+            return false;
+        }
+
+        self.update(column);
+        return true;
+    }
+
+    /// columns played since the position was created, the last one is the last move
+    pub fn history(&self) -> Vec<usize> {
+        let mut history = Vec::new();
+        let mut last = self.last;
+        let mut heights = [0; COLUMNS];
+        while last != 0 {
+            let column = last as usize - 1;
+            let row = (0..ROWS).filter(|&row| self.board[row][column] != 0).nth(heights[column]).unwrap();
+            heights[column] += 1;
+            history.push(column);
+            last = self.previous[row][column];
+        }
+        history.reverse();
+
+        return history;
+    }
+
+    /// numbers of lines of CONNECT cells with CONNECT-1 and CONNECT-2 tokens of `player` and no token of the opponent
+    pub fn count_open_lines(&self, player: i8) -> (usize, usize) {
+        let (mut threes, mut twos) = (0usize, 0usize);
+
+        for (drow, dcol) in [(0i32, 1i32), (1, 0), (1, 1), (1, -1)] {
+            for row in 0..ROWS as i32 {
+                for col in 0..COLUMNS as i32 {
+                    let (last_row, last_col) = (row + (CONNECT as i32 - 1)*drow, col + (CONNECT as i32 - 1)*dcol);
+                    if last_row >= ROWS as i32 || last_col < 0 || last_col >= COLUMNS as i32 {
+                        continue;
+                    }
+
+                    let cells = (0..CONNECT as i32).map(|i| self.board[(row + i*drow) as usize][(col + i*dcol) as usize]);
+                    if cells.clone().any(|cell| cell == -player) {
+                        continue;
+                    }
+
+                    let own = cells.filter(|&cell| cell == player).count();
+                    if own + 1 == CONNECT { threes += 1; }
+                    else if own + 2 == CONNECT { twos += 1; }
+                }
             }
-            write!(f, "\n").unwrap();
         }
-        write!(f, "")
+
+        return (threes, twos);
     }
-}
 
-impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> GeneralGame for Connect4<ROWS,COLUMNS,CONNECT> {
-    fn get_score(&self) -> i8 {
+    /// 1 or -1 if the player has CONNECT tokens in a line, 0 otherwise
+    fn winner(&self) -> i8 {
         let mut count_1 : usize;
         let mut count_n1 : usize;
 
@@ -147,19 +227,168 @@ impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> GeneralGame for C
         return 0;
     }
 
+    /// row in which a token dropped into `column` lands
+    fn landing_row(&self, column: usize) -> Option<usize> {
+        return (0..ROWS).rev().find(|&row| self.board[row][column] == 0);
+    }
+}
+
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> fmt::Display for Connect4<ROWS,COLUMNS,CONNECT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..ROWS{
+            for col in 0..COLUMNS{
+                write!(f, "{} ", if self.board[row][col] == 1 {'X'} else if self.board[row][col] == -1 {'O'} else {'.'}).unwrap();
+            }
+            writeln!(f).unwrap();
+        }
+        write!(f, "")
+    }
+}
+
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> GeneralGame for Connect4<ROWS,COLUMNS,CONNECT> {
+    /// the column, counted from the left starting at 0
+    type Move = usize;
+
+    fn get_outcome(&self) -> Outcome {
+        let winner = self.winner();
+        if winner != 0 {
+            return Outcome::Win(winner);
+        }
+        if self.board[0].iter().all(|&cell| cell != 0) {
+            return Outcome::Draw;
+        }
+
+        return Outcome::Ongoing;
+    }
+
     fn get_available(&self) -> Vec<usize> {
         return Vec::from_iter( (0..COLUMNS).filter(|&col| self.board[0][col] == 0) );
     }
 
-    fn update(&mut self, index:usize, player:i8) {
-        for row in (0..ROWS).rev() {
-            if self.board[row][index] == 0 {
-                self.board[row][index] = player;
-                return;
+    fn update(&mut self, index:usize) {
+        let row = self.landing_row(index).expect("Out of range.");
+        self.board[row][index] = self.player;
+        self.player = -self.player;
+        self.previous[row][index] = self.last;
+        self.last = index as u8 + 1;
+    }
+
+    fn undo(&mut self) -> Option<usize> {
+        let index = self.last_move()?;
+        let row = (0..ROWS).find(|&row| self.board[row][index] != 0).unwrap();
+        self.board[row][index] = 0;
+        self.player = -self.player;
+        self.last = self.previous[row][index];
+
+        return Some(index);
+    }
+
+    fn last_move(&self) -> Option<usize> {
+        return (self.last as usize).checked_sub(1);
+    }
+
+    fn player(&self) -> i8 {
+        return self.player;
+    }
+
+    /// drops a token of `player` into every column without passing the turn, so that it works for both players
+    fn winning_moves(&self, player: i8) -> Vec<usize> {
+        let mut game = self.clone();
+        return Vec::from_iter((0..COLUMNS).filter(|&index| {
+            let Some(row) = self.landing_row(index) else { return false };
+            game.board[row][index] = player;
+            let won = game.winner() == player;
+            game.board[row][index] = 0;
+            won
+        }));
+    }
+
+    /// prefers the central columns, the weight is 1 at the edges and increases by 1 towards the center
+    fn move_weight(&self, index: usize, _player: i8) -> f32 {
+        return 1. + index.min(COLUMNS - 1 - index) as f32;
+    }
+
+    /// sums the value of all lines of CONNECT cells going through the token, lines which can still be completed count more
+    /// the more tokens of `player` they contain, and lines of the opponent count when the token blocks them
+    fn move_heuristic(&self, index: usize, player: i8) -> f32 {
+        const WIN: f32 = 20.;
+        const BLOCK: f32 = 10.;
+        const UNIT: f32 = 0.25;
+
+        let row = match self.landing_row(index) {
+            Some(row) => row as i32,
+            None => return f32::NEG_INFINITY
+        };
+        let col = index as i32;
+
+        let mut heuristic = 0.;
+        for (drow, dcol) in [(0i32, 1i32), (1, 0), (1, 1), (1, -1)] {
+            // each line is identified by how many cells it starts before the token
+            for start in 0..CONNECT as i32 {
+                let cells = (0..CONNECT as i32).map(|i| (row + (i - start)*drow, col + (i - start)*dcol));
+                if ! cells.clone().all(|(r, c)| r >= 0 && r < ROWS as i32 && c >= 0 && c < COLUMNS as i32) {
+                    continue;
+                }
+
+                let own = cells.clone().filter(|&(r, c)| self.board[r as usize][c as usize] == player).count();
+                let opponent = cells.filter(|&(r, c)| self.board[r as usize][c as usize] == -player).count();
+
+                if opponent == 0 {
+                    if own == CONNECT - 1 { heuristic += WIN; }
+                    else { heuristic += UNIT * ((own + 1)*(own + 1)) as f32; }
+                }
+                else if own == 0 {
+                    if opponent == CONNECT - 1 { heuristic += BLOCK; }
+                    else { heuristic += UNIT * (opponent*opponent) as f32; }
+                }
             }
         }
 
-        panic!("Out of range.");
+        return heuristic;
+    }
+}
+
+/// Values a position by the difference of open lines, `tanh(three * (own threes - opponent threes) + two * (own twos - opponent twos))`,
+/// finished games are valued exactly
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreatEvaluator {
+    pub three: f32,
+    pub two: f32
+}
+
+impl Default for ThreatEvaluator {
+    fn default() -> ThreatEvaluator {
+        return ThreatEvaluator { three: 0.3, two: 0.05 };
+    }
+}
+
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> Evaluator<Connect4<ROWS,COLUMNS,CONNECT>> for ThreatEvaluator {
+    fn evaluate(&self, game: &Connect4<ROWS,COLUMNS,CONNECT>, player: i8) -> f32 {
+        let outcome = game.get_outcome();
+        if outcome.is_over() {
+            return (outcome.winner() * player) as f32;
+        }
+
+        let (own_threes, own_twos) = game.count_open_lines(player);
+        let (threes, twos) = game.count_open_lines(-player);
+
+        return (self.three * (own_threes as f32 - threes as f32) + self.two * (own_twos as f32 - twos as f32)).tanh();
+    }
+}
+
+/// the cells with tokens of the player to move followed by the cells with tokens of the opponent, row by row
+impl<const ROWS:usize,const COLUMNS:usize,const CONNECT:usize> Encode for Connect4<ROWS,COLUMNS,CONNECT> {
+    fn encode(&self, player: i8) -> Vec<f32> {
+        let cells = self.board.iter().flatten();
+        return Vec::from_iter([player, -player].into_iter().flat_map(|token| cells.clone().map(move |&cell| if cell == token { 1. } else { 0. })));
+    }
+
+    fn policy_index(&self, index: usize) -> usize {
+        return index;
+    }
+
+    fn policy_size(&self) -> usize {
+        return COLUMNS;
     }
 }
 
@@ -173,7 +402,7 @@ fn test_connect4_fromstr() {
                         XO.XX.\n\
                         XX.OOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str), Some(Connect4 {board: [[1,0,0,0,0,0],[-1,0,0,0,0,0],[1,0,0,-1,0,0],[-1,0,0,1,0,0],[1,-1,0,1,1,0],[1,1,0,-1,-1,-1]]}));
+    assert_eq!(Connect4::<6,6,4>::from_string(str), Connect4::from_board([[1,0,0,0,0,0],[-1,0,0,0,0,0],[1,0,0,-1,0,0],[-1,0,0,1,0,0],[1,-1,0,1,1,0],[1,1,0,-1,-1,-1]]));
 
     let str = "\
                         X..O..\n\
@@ -202,7 +431,7 @@ fn test_connect4_fromstr() {
                         XOOXXO.\n\
                         XOXOXO.\n\
                     ";
-    assert_eq!(Connect4::<6,7,4>::from_string(str), Some(Connect4 {board: [[1,0,1,-1,0,0,0],[-1,0,-1,1,0,0,0],[1,-1,-1,-1,1,0,0],[-1,1,1,1,-1,-1,0],[1,-1,-1,1,1,-1,0],[1,-1,1,-1,1,-1,0]]}));
+    assert_eq!(Connect4::<6,7,4>::from_string(str), Connect4::from_board([[1,0,1,-1,0,0,0],[-1,0,-1,1,0,0,0],[1,-1,-1,-1,1,0,0],[-1,1,1,1,-1,-1,0],[1,-1,-1,1,1,-1,0],[1,-1,1,-1,1,-1,0]]));
 }
 
 #[test]
@@ -215,7 +444,7 @@ fn test_connect4_score() {
                         XO.XX.\n\
                         OOOOOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), -1);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(-1));
 
     let str = "\
                         X.....\n\
@@ -225,7 +454,7 @@ fn test_connect4_score() {
                         XO.XX.\n\
                         XX.OOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), 0);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Ongoing);
 
     let str = "\
                         X.....\n\
@@ -235,7 +464,7 @@ fn test_connect4_score() {
                         XO.XX.\n\
                         XX.OOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), 1, "vertical");
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(1), "vertical");
 
     let str = "\
                         X.X...\n\
@@ -245,7 +474,7 @@ fn test_connect4_score() {
                         XOOXXO\n\
                         OOXOXO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), -1, "diagonal y=x");
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(-1), "diagonal y=x");
 
     let str = "\
                         X.XO...\n\
@@ -255,7 +484,7 @@ fn test_connect4_score() {
                         XOOXXO.\n\
                         XOXOXO.\n\
                     ";
-    assert_eq!(Connect4::<6,7,4>::from_string(str).unwrap().get_score(), -1, "diagonal y=x");
+    assert_eq!(Connect4::<6,7,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(-1), "diagonal y=x");
 
     let str = "\
                         X.XO...\n\
@@ -265,7 +494,7 @@ fn test_connect4_score() {
                         XOOXOOX\n\
                         XOXOXOX\n\
                     ";
-    assert_eq!(Connect4::<6,7,4>::from_string(str).unwrap().get_score(), -1, "diagonal y=x");
+    assert_eq!(Connect4::<6,7,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(-1), "diagonal y=x");
 
     let str = "\
                         X.....\n\
@@ -275,7 +504,7 @@ fn test_connect4_score() {
                         XOOXX.\n\
                         XXOOOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), -1, "diagonal y=-x");
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(-1), "diagonal y=-x");
 
     let str = "\
                         X..O..\n\
@@ -285,7 +514,7 @@ fn test_connect4_score() {
                         XOOXX.\n\
                         OOOXOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), -1);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(-1));
 
     let str = "\
                         X.....\n\
@@ -295,7 +524,7 @@ fn test_connect4_score() {
                         XOXXX.\n\
                         OOOXOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_score(), 1);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(1));
 
     let str = "\
                         O.X...\n\
@@ -304,7 +533,10 @@ fn test_connect4_score() {
                         XOXOXX\n\
                         OOOXOO\n\
                     ";
-    assert_eq!(Connect4::<5,6,4>::from_string(str).unwrap().get_score(), 1);
+    assert_eq!(Connect4::<5,6,4>::from_string_unchecked(str).unwrap().get_outcome(), Outcome::Win(1));
+
+    // a full board without a line
+    assert_eq!(Connect4::<2,3,3>::from_string("XOX\nXOO\n").unwrap().get_outcome(), Outcome::Draw);
 }
 
 #[test]
@@ -317,7 +549,7 @@ fn test_connect4_available() {
                         XO.XX.\n\
                         OOOOOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_available(), [1,2,3,4,5]);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_available(), [1,2,3,4,5]);
 
     let str = "\
                         X..OX.\n\
@@ -327,7 +559,7 @@ fn test_connect4_available() {
                         XO.XX.\n\
                         XX.OOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_available(), [1,2,5]);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_available(), [1,2,5]);
 
     let str = "\
                         ......\n\
@@ -337,7 +569,7 @@ fn test_connect4_available() {
                         XO.XX.\n\
                         XX.OOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_available(), [0,1,2,3,4,5]);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_available(), [0,1,2,3,4,5]);
 
     let str = "\
                         XXOXXO\n\
@@ -347,7 +579,7 @@ fn test_connect4_available() {
                         XOXXXX\n\
                         XXXOOO\n\
                     ";
-    assert_eq!(Connect4::<6,6,4>::from_string(str).unwrap().get_available(), []);
+    assert_eq!(Connect4::<6,6,4>::from_string_unchecked(str).unwrap().get_available(), []);
 
     let str = "\
                         O..XO..\n\
@@ -356,17 +588,19 @@ fn test_connect4_available() {
                         XO.XX..\n\
                         XX.OOO.\n\
                     ";
-    assert_eq!(Connect4::<5,7,4>::from_string(str).unwrap().get_available(), [1,2,5,6]);
+    assert_eq!(Connect4::<5,7,4>::from_string_unchecked(str).unwrap().get_available(), [1,2,5,6]);
 }
 
 #[test]
 fn test_connect4_fmt(){
-    let connect4 = Connect4::<6,6,4>{ board: [[-1,0,0,1,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,1,0,-1,1],[-1,0,1,0,0,-1]]};
+    let mut connect4 = Connect4::<6,6,4>::empty();
+    connect4.board = [[-1,0,0,1,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,0,0,0,0],[0,0,1,0,-1,1],[-1,0,1,0,0,-1]];
 
     let connect4_str = format!("{}", connect4);
     assert_eq!(connect4_str, "O . . X . . \n. . . . . . \n. . . . . . \n. . . . . . \n. . X . O X \nO . X . . O \n");
 
-    let connect4 = Connect4::<3,6,4>{ board: [[0,0,0,0,0,0],[0,0,1,0,-1,1],[-1,0,1,0,0,-1]]};
+    let mut connect4 = Connect4::<3,6,4>::empty();
+    connect4.board = [[0,0,0,0,0,0],[0,0,1,0,-1,1],[-1,0,1,0,0,-1]];
 
     let connect4_str = format!("{}", connect4);
     assert_eq!(connect4_str, ". . . . . . \n. . X . O X \nO . X . . O \n");
@@ -380,49 +614,183 @@ fn test_connect4_update(){
                         X..O..\n\
                         O..X..\n\
                         XO.XX.\n\
-                        OOOOO.\n\
+                        OXOXO.\n\
                     ";
     let connect4 = Connect4::<6,6,4>::from_string(str).unwrap();
+    assert_eq!(connect4.player(), -1);
+    assert_eq!(connect4.last_move(), None);
 
     str = "\
                         X.....\n\
                         O.....\n\
                         X..O..\n\
-                        OX.X..\n\
+                        OO.X..\n\
                         XO.XX.\n\
-                        OOOOO.\n\
+                        OXOXO.\n\
                     ";
     let mut test = connect4.clone();
-    test.update(1, 1);
+    test.update(1);
     assert_eq!(test, Connect4::from_string(str).unwrap());
 
+    let after_first = test.clone();
     str = "\
                         X.....\n\
                         O.....\n\
                         X..O..\n\
-                        O..X..\n\
+                        OO.X..\n\
                         XO.XX.\n\
-                        OOOOOO\n\
+                        OXOXOX\n\
                     ";
-    test = connect4.clone();
-    test.update(5, -1);
+    test.update(5);
     assert_eq!(test, Connect4::from_string(str).unwrap());
+    assert_eq!(test.player(), -1);
+    assert_eq!(test.last_move(), Some(5));
+    assert_eq!(test.history(), [1, 5]);
+
+    assert_eq!(test.undo(), Some(5));
+    assert_eq!(test, after_first);
+    assert_eq!(test.undo(), Some(1));
+    assert_eq!(test, connect4);
+    assert_eq!(test.undo(), None);
+
+    // the first column is full
+    assert_eq!(test.parse_move("1"), Some(1));
+    assert_eq!(test.parse_move("0"), None);
+    assert_eq!(test.parse_move("6"), None);
+    assert_eq!(test.parse_move("left"), None);
+}
+
+#[test]
+fn test_connect4_turns(){
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        O......\n\
+                        XX.....\n\
+                    ";
+    let mut connect4 = Connect4::<6,7,4>::from_string(str).unwrap();
+    assert_eq!(connect4.player(), -1);
+
+    assert!(! connect4.play(2, 1));
+    assert!(connect4.play(2, -1));
+    assert!(! connect4.play(7, 1));
+    assert_eq!(connect4.history(), [2]);
+
+    // more tokens of O than of X, or two tokens more of X
+    assert_eq!(Connect4::<6,7,4>::from_string(&str.replace("XX.....", "OX.....")), None);
+    assert_eq!(Connect4::<6,7,4>::from_string(&str.replace("XX.....", "XXX....")), None);
+
+    // no more moves once the game is won
+    let mut won = Connect4::<6,7,4>::from_string(&str.replace("O......", "OOO....").replace("XX.....", "XXXX...")).unwrap();
+    assert!(! won.play(4, -1));
+    assert_eq!(won.winning_moves(-1), [3]);
+    assert_eq!(won.winning_moves(1), [0, 1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_connect4_move_weight(){
+    let connect4 = Connect4::<6,7,4>::empty();
+    let weights: Vec<f32> = (0..7).map(|col| connect4.move_weight(col, 1)).collect();
+    assert_eq!(weights, [1.,2.,3.,4.,3.,2.,1.]);
+
+    let connect4 = Connect4::<6,6,4>::empty();
+    let weights: Vec<f32> = (0..6).map(|col| connect4.move_weight(col, 1)).collect();
+    assert_eq!(weights, [1.,2.,3.,3.,2.,1.]);
+}
+
+#[test]
+fn test_connect4_move_heuristic(){
+    let connect4 = Connect4::<6,7,4>::empty();
+    // the central column is part of the most lines
+    assert!(connect4.move_heuristic(3, 1) > connect4.move_heuristic(2, 1));
+    assert!(connect4.move_heuristic(2, 1) > connect4.move_heuristic(0, 1));
+    assert_eq!(connect4.move_heuristic(0, 1), connect4.move_heuristic(6, -1));
+
+    let str = "\
+                        ......X\n\
+                        ......O\n\
+                        ......X\n\
+                        O.....O\n\
+                        O.....X\n\
+                        O.XXX.O\n\
+                    ";
+    let connect4 = Connect4::<6,7,4>::from_string(str).unwrap();
+    // winning is better than blocking, which is better than anything else
+    let heuristics: Vec<f32> = (0..6).map(|col| connect4.move_heuristic(col, -1)).collect();
+    assert!(heuristics[0] > heuristics[1]);
+    assert!(heuristics[1] > heuristics[4]);
+    assert!(heuristics[1] > heuristics[2]);
+    assert_eq!(connect4.move_heuristic(6, -1), f32::NEG_INFINITY);
+}
+
+#[test]
+fn test_connect4_count_open_lines() {
+    assert_eq!(Connect4::<6,7,4>::empty().count_open_lines(1), (0, 0));
+
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .XX..O.\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string_unchecked(str).unwrap();
+    // the two horizontal lines without O through both X
+    assert_eq!(game.count_open_lines(1), (0, 2));
+    assert_eq!(game.count_open_lines(-1), (0, 0));
+
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        X......\n\
+                        X......\n\
+                        X..O...\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string_unchecked(str).unwrap();
+    // the vertical line of rows 2-5 has three and rows 1-4 two, the bottom row is blocked
+    assert_eq!(game.count_open_lines(1), (1, 1));
+}
+
+#[test]
+fn test_threat_evaluator() {
+    let evaluator = ThreatEvaluator::default();
+    assert_eq!(evaluator.evaluate(&Connect4::<6,7,4>::empty(), 1), 0.);
+
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        X......\n\
+                        X......\n\
+                        X..O...\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string_unchecked(str).unwrap();
+    let value = evaluator.evaluate(&game, -1);
+    assert!(value < -0.2 && value > -1.);
+    assert_eq!(evaluator.evaluate(&game, 1), -value);
+
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        X......\n\
+                        X......\n\
+                        X......\n\
+                        X..OOO.\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string_unchecked(str).unwrap();
+    assert_eq!(evaluator.evaluate(&game, -1), -1.);
+    assert_eq!(evaluator.evaluate(&game, 1), 1.);
+}
+
+#[test]
+fn test_connect4_encode(){
+    let game = Connect4::<2,3,2>::from_string("...\nXO.\n").unwrap();
+
+    assert_eq!(game.encode(1), vec![0., 0., 0., 1., 0., 0., 0., 0., 0., 0., 1., 0.]);
+    assert_eq!(game.encode(-1), vec![0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0.]);
+}
 
-    str = "\
-                O......\n\
-                X..O...\n\
-                O..X..X\n\
-                XO.XX.O\n\
-                OOOOOOX\n\
-            ";
-    let mut test = Connect4::<5,7,4>::from_string(&str).unwrap();
-    test.update(6, -1);
-    str = "\
-                O......\n\
-                X..O..O\n\
-                O..X..X\n\
-                XO.XX.O\n\
-                OOOOOOX\n\
-            ";
-    assert_eq!(test, Connect4::from_string(str).unwrap());
-}
\ No newline at end of file
@@ -0,0 +1,116 @@
+use rand::Rng;
+
+use super::clock::Clock;
+use super::evaluation::Evaluator;
+use super::mcts::{GeneralGame, Outcome, SearchConfig};
+use super::move_selection::MoveSelection;
+use super::rollout::RolloutPolicy;
+use super::selection::SelectionPolicy;
+use super::tree::Tree;
+
+/// How long an engine may think about a move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    /// iterations of the MCTS, visited nodes of the alpha-beta search
+    Nodes(usize),
+    /// milliseconds measured by the clock of the engine
+    Milliseconds(f64)
+}
+
+/// Anything which picks moves, so that different kinds of bots can play against each other
+pub trait Engine<T: GeneralGame> {
+    /// the move of the player to move in `game`, `None` if the game is over
+    fn choose_move(&mut self, game: &T, budget: Budget) -> Option<T::Move>;
+}
+
+/// MCTS with a new `Tree` for every move
+pub struct MctsEngine<S: SelectionPolicy, R: RolloutPolicy, E, C: Clock, G: Rng> {
+    pub config: SearchConfig<S, R, E>,
+    pub move_selection: MoveSelection,
+    pub rollouts: usize,
+    pub clock: C,
+    pub rng: G
+}
+
+impl<S: SelectionPolicy, R: RolloutPolicy, E, C: Clock, G: Rng> MctsEngine<S, R, E, C, G> {
+    /// one rollout per iteration and the most visited move
+    pub fn new(config: SearchConfig<S, R, E>, clock: C, rng: G) -> MctsEngine<S, R, E, C, G> {
+        return MctsEngine { config, move_selection: MoveSelection::default(), rollouts: 1, clock, rng };
+    }
+}
+
+impl<T, S, R, E, C, G> Engine<T> for MctsEngine<S, R, E, C, G>
+where T: GeneralGame, S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, C: Clock, G: Rng {
+    fn choose_move(&mut self, game: &T, budget: Budget) -> Option<T::Move> {
+        let mut tree = Tree::new(game.clone());
+        match budget {
+            Budget::Nodes(iters) => tree.predict(iters, self.rollouts, &self.config, &mut self.rng),
+            Budget::Milliseconds(budget_ms) => tree.predict_for(budget_ms, self.rollouts, &self.config, &mut self.clock, &mut self.rng)
+        };
+
+        let child = tree.choose_move(&self.move_selection, self.rollouts, &self.config, &mut self.rng)?;
+        return Some(tree.node(child).move_index);
+    }
+}
+
+/// Plays a game between two engines from `game`, `first` plays for player 1 and `second` for player -1.
+/// Returns the outcome once the game is over.
+pub fn play<T: GeneralGame>(mut game: T, first: &mut dyn Engine<T>, second: &mut dyn Engine<T>, budget: Budget) -> Outcome {
+    while ! game.get_outcome().is_over() {
+        let index = if game.player() == 1 { first.choose_move(&game, budget) } else { second.choose_move(&game, budget) };
+        let index = index.expect("the engine did not find a move");
+        assert!(game.get_available().contains(&index), "the engine played an illegal move");
+
+        game.update(index);
+    }
+
+    return game.get_outcome();
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::clock::FakeClock;
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::Ucb1;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+#[test]
+fn test_mcts_engine(){
+    let mut engine = MctsEngine::new(SearchConfig::<Ucb1, UniformRollout>::default(), FakeClock { time: 0., step: 1. }, StdRng::seed_from_u64(0));
+
+    // X has to complete the top row
+    let game = TicTacToe::from_string("XX.\nOO.\n...").unwrap();
+    assert_eq!(engine.choose_move(&game, Budget::Nodes(200)), Some(Square(0,2)));
+    assert_eq!(engine.choose_move(&game, Budget::Milliseconds(200.)), Some(Square(0,2)));
+
+    let over = TicTacToe::from_string("XXX\nOO.\n...").unwrap();
+    assert_eq!(engine.choose_move(&over, Budget::Nodes(200)), None);
+}
+
+/// plays the first available move
+#[cfg(test)]
+struct FirstMove;
+
+#[cfg(test)]
+impl<T: GeneralGame> Engine<T> for FirstMove {
+    fn choose_move(&mut self, game: &T, _budget: Budget) -> Option<T::Move> {
+        return game.get_available().first().copied();
+    }
+}
+
+#[test]
+fn test_play(){
+    let game = TicTacToe::from_string("...\n...\n...").unwrap();
+
+    // X takes a1, c1, b2 and a3 and wins on the diagonal
+    assert_eq!(play(game.clone(), &mut FirstMove, &mut FirstMove, Budget::Nodes(0)), Outcome::Win(1));
+
+    let mut engine = MctsEngine::new(SearchConfig::<Ucb1, UniformRollout>::default(), FakeClock { time: 0., step: 1. }, StdRng::seed_from_u64(0));
+    assert_eq!(play(game.clone(), &mut FirstMove, &mut engine, Budget::Nodes(2000)), Outcome::Win(-1));
+    assert_eq!(play(game, &mut engine, &mut FirstMove, Budget::Nodes(2000)), Outcome::Win(1));
+}
@@ -0,0 +1,155 @@
+use rand::Rng;
+
+use super::mcts::{truncated_playout, GeneralGame, SearchConfig};
+use super::rollout::RolloutPolicy;
+use super::selection::SelectionPolicy;
+
+/// Static evaluation of a position, used instead of or after a truncated rollout
+pub trait Evaluator<T: GeneralGame> {
+    /// value of `game` for `player`, who is to move, between -1 (certain loss) and 1 (certain win)
+    fn evaluate(&self, game: &T, player: i8) -> f32;
+
+    /// Prior probabilities of the moves in the order of `game.get_available()` together with the value as in `evaluate`,
+    /// used when the selection policy scores children by their priors. Evaluators without a policy return `None`.
+    fn evaluate_with_priors(&self, game: &T, player: i8) -> (Option<Vec<f32>>, f32) {
+        return (None, self.evaluate(game, player));
+    }
+}
+
+/// Evaluates every position as even, the default which is not used unless `LeafEvaluation` asks for it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NoEvaluator;
+
+impl<T: GeneralGame> Evaluator<T> for NoEvaluator {
+    fn evaluate(&self, _game: &T, _player: i8) -> f32 {
+        return 0.;
+    }
+}
+
+/// How the search values a new leaf. The rollout is stopped after `cutoff` plies and the position reached is evaluated,
+/// the result is then blended with the evaluation of the leaf itself as `weight * leaf + (1 - weight) * rollout`.
+/// By default rollouts are played to the end and the evaluator is not used.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LeafEvaluation<E> {
+    pub evaluator: E,
+    /// plies played by the rollout before its position is evaluated, `None` plays to the end of the game
+    pub cutoff: Option<usize>,
+    /// weight of the evaluation of the leaf between 0 and 1, with 1 no rollout is played
+    pub weight: f32
+}
+
+/// Priors of the children of a leaf which is expanded and the evaluation of the leaf, computed together by the evaluator.
+/// `None` if the selection policy does not use priors, in which case the children keep uniform priors.
+pub(crate) fn evaluate_expansion<T, S, R, E>(game: &T, player: i8, config: &SearchConfig<S, R, E>) -> Option<(Option<Vec<f32>>, f32)>
+where T: GeneralGame, S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T> {
+    if ! config.selection.uses_priors() {
+        return None;
+    }
+
+    return Some(config.leaf.evaluator.evaluate_with_priors(game, player));
+}
+
+/// Simulation from a new leaf as configured by `config.leaf`, see `LeafEvaluation`. An evaluation between -1 and 1 is turned
+/// into a win of one of the players at random with the same expected reward, so that the statistics keep counting wins.
+/// `leaf_value` is the evaluation of the leaf if it is already known. The rollout is played on `game` and taken back.
+/// Returns the winner, or 0 for a draw.
+pub(crate) fn simulate<T, S, R, E, G>(game: &mut T, player: i8, config: &SearchConfig<S, R, E>, rng: &mut G, moves: Option<&mut Vec<(T::Move, i8)>>, leaf_value: Option<f32>) -> i8
+where T: GeneralGame, S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized {
+    let leaf = &config.leaf;
+    let cutoff = leaf.cutoff.unwrap_or(usize::MAX);
+
+    // value for player 1
+    let mut value = 0.;
+    if leaf.weight < 1. {
+        let evaluate = |game: &T, player: i8| player as f32 * leaf.evaluator.evaluate(game, player);
+        value = match truncated_playout(game, player, &config.rollout, rng, moves, cutoff, evaluate) {
+            Ok(winner) if leaf.weight == 0. => return winner,
+            Ok(winner) => winner as f32,
+            Err(value) => value
+        };
+    }
+    if leaf.weight > 0. {
+        let leaf_value = leaf_value.unwrap_or_else(|| leaf.evaluator.evaluate(game, player));
+        value = leaf.weight * player as f32 * leaf_value + (1. - leaf.weight) * value;
+    }
+
+    return if rng.gen::<f32>() < (1. + value) / 2. { 1 } else { -1 };
+}
+
+
+#[cfg(test)]
+use super::connect4::{Connect4, ThreatEvaluator};
+#[cfg(test)]
+use super::tree::Tree;
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::Ucb1;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+/// evaluates every position as won by player 1
+#[cfg(test)]
+struct PlayerOneWins;
+
+#[cfg(test)]
+impl<T: GeneralGame> Evaluator<T> for PlayerOneWins {
+    fn evaluate(&self, _game: &T, player: i8) -> f32 {
+        return player as f32;
+    }
+}
+
+#[test]
+fn test_simulate(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut game = Connect4::<6,7,4>::empty();
+
+    // without a cutoff and weight this is a plain rollout, which is taken back afterwards
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut moves = Vec::new();
+    assert!([-1, 0, 1].contains(&simulate(&mut game, 1, &config, &mut rng, Some(&mut moves), None)));
+    assert!(moves.len() >= 7);
+    assert_eq!(game.history(), []);
+
+    // the evaluation decides when no rollout is played or it is cut right away
+    for (cutoff, weight) in [(None, 1.), (Some(0), 0.), (Some(0), 0.5)] {
+        let config = SearchConfig::<Ucb1, UniformRollout, PlayerOneWins> {
+            leaf: LeafEvaluation { evaluator: PlayerOneWins, cutoff, weight }, selection: Ucb1::default(), rollout: UniformRollout, draw_value: 0., early_stop: Default::default()
+        };
+        for player in [1, -1] {
+            assert!((0..100).all(|_| simulate(&mut game, player, &config, &mut rng, None, None) == 1));
+        }
+    }
+
+    // the evaluation of the even position gives each player half of the wins
+    let config = SearchConfig::<Ucb1, UniformRollout> { leaf: LeafEvaluation { evaluator: NoEvaluator, cutoff: Some(0), weight: 0. }, ..Default::default() };
+    let wins: i32 = (0..1000).map(|_| simulate(&mut game, 1, &config, &mut rng, None, None) as i32).sum();
+    assert!(wins.abs() < 100);
+}
+
+#[test]
+fn test_search_with_evaluation(){
+    let mut rng = StdRng::seed_from_u64(0);
+    // O has to block the last column
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ......X\n\
+                        O.....X\n\
+                        O.....X\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+
+    for (cutoff, weight) in [(None, 1.), (Some(4), 0.), (Some(4), 0.5)] {
+        let config = SearchConfig::<Ucb1, UniformRollout, ThreatEvaluator> { leaf: LeafEvaluation { evaluator: ThreatEvaluator::default(), cutoff, weight }, ..Default::default() };
+        let mut tree = Tree::new(game.clone());
+        tree.predict(500, 1, &config, &mut rng);
+
+        let best = tree.get_most_visited_child(Tree::<Connect4<6,7,4>>::ROOT).unwrap();
+        assert_eq!(tree.node(best).move_index, 6, "{:?} {}", cutoff, weight);
+        let stats = tree.root().stats;
+        assert_eq!(stats.visits, stats.wins + stats.losses + stats.draws);
+    }
+}
+
@@ -1,23 +1,82 @@
 use wasm_bindgen::prelude::*;
-use connect4::Connect4;
-use mcts::GeneralGame;
-use mcts::Node;
+use alpha_beta::AlphaBeta;
+use clock::{Clock, DefaultClock};
+use connect4::{Connect4, ThreatEvaluator};
+use engine::{Budget, Engine};
+use mcts::{GeneralGame, Outcome};
+use evaluation::LeafEvaluation;
+use mcts::{clamp_draw_value, EarlyStop, SearchConfig};
+use move_selection::MoveSelection;
+use opening_book::OpeningBook;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use report::SearchReport;
+use rollout::{Rollout, UniformRollout};
+use selection::{Rave, RaveSchedule, Selection, Ucb1};
+use solver::Solver;
+use std::sync::OnceLock;
+use tree::{NodeLimit, Tree};
 
-mod connect4;
-mod mcts;
+pub mod alpha_beta;
+pub mod clock;
+pub mod connect4;
+pub mod engine;
+pub mod evaluation;
+pub mod mcts;
+pub mod move_selection;
+pub mod network;
+pub mod opening_book;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod report;
+pub mod rollout;
+pub mod selection;
+pub mod self_play;
+pub mod solver;
+pub mod tree;
 
+/// parses `board`, `None` if it is not valid or not the turn of `player`
+fn parse_board(board: &str, player: i8) -> Option<Connect4<6,7,4>> {
+    return Connect4::<6,7,4>::from_string(board).filter(|board| board.player() == player);
+}
+
+/// built by `examples/opening_book.rs` with a seeded search of 100000 iterations for every position
+/// of the first 4 plies:
+/// `cargo run --release --example opening_book -- book/connect4.book 4 100000 0`
+static OPENING_BOOK: &[u8] = include_bytes!("../book/connect4.book");
+
+fn opening_book() -> &'static OpeningBook<6,7,4> {
+    static BOOK: OnceLock<OpeningBook<6,7,4>> = OnceLock::new();
+    return BOOK.get_or_init(|| OpeningBook::from_bytes(OPENING_BOOK).expect("the embedded opening book is invalid"));
+}
+
+/// plays the move of the opening book if the position is in it, searches otherwise. `None` if the board is not valid or it is
+/// not the turn of `player`, X moves first
 #[wasm_bindgen]
 pub fn predict_best_move(board: &str, player:i8, iters: usize) -> Option<usize> {
-    let board_opt = Connect4::<6,7,4>::from_string(board);
+    return search_best_move(board, player, iters, &mut rand::thread_rng());
+}
+
+/// Same as `predict_best_move`, but the search is reproducible for a given `seed`
+#[wasm_bindgen]
+pub fn predict_best_move_seeded(board: &str, player:i8, iters: usize, seed: u64) -> Option<usize> {
+    return search_best_move(board, player, iters, &mut StdRng::seed_from_u64(seed));
+}
+
+fn search_best_move<G: Rng + ?Sized>(board: &str, player:i8, iters: usize, rng: &mut G) -> Option<usize> {
+    let board_opt = parse_board(board, player);
+    if let Some(col) = board_opt.as_ref().and_then(|board| opening_book().lookup(board)) {
+        return Some(col);
+    }
 
     if let Some(board) = board_opt {
-        let mut root_node = Node::<Connect4<6,7,4>>::new(board, player, 0);
+        let mut tree = Tree::new(board);
 
-        root_node.predict(iters, 1);
+        tree.predict(iters, 1, &SearchConfig::<Ucb1, UniformRollout>::default(), rng);
 
-        let child = root_node.get_most_visited_child();
+        let child = tree.get_most_visited_child(Tree::<Connect4<6,7,4>>::ROOT);
         if let Some(child) = child {
-            return Some(child.move_index);
+            return Some(tree.node(child).move_index);
         }
     }
 
@@ -25,23 +84,283 @@ pub fn predict_best_move(board: &str, player:i8, iters: usize) -> Option<usize>
 }
 
 #[wasm_bindgen]
-pub fn get_score(board: &str) -> Option<i8> {
-    let board_opt = Connect4::<6,7,4>::from_string(board);
+pub fn predict_best_move_timed(board: &str, player:i8, time_ms: f64) -> Option<usize> {
+    return search_best_move_for(board, player, time_ms, &mut DefaultClock::new(), &mut rand::thread_rng());
+}
+
+fn search_best_move_for<C: Clock, G: Rng + ?Sized>(board: &str, player:i8, time_ms: f64, clock: &mut C, rng: &mut G) -> Option<usize> {
+    let board_opt = parse_board(board, player);
 
     if let Some(board) = board_opt {
-        return Some(board.get_score());
+        let mut tree = Tree::new(board);
+
+        tree.predict_for(time_ms, 1, &SearchConfig::<Ucb1, UniformRollout>::default(), clock, rng);
+
+        let child = tree.get_most_visited_child(Tree::<Connect4<6,7,4>>::ROOT);
+        if let Some(child) = child {
+            return Some(tree.node(child).move_index);
+        }
     }
 
     return None;
 }
 
+/// Best move of the alpha-beta search with `ThreatEvaluator`, searching for `time_ms` milliseconds
+#[wasm_bindgen]
+pub fn predict_best_move_alpha_beta(board: &str, player: i8, time_ms: f64) -> Option<usize> {
+    let board = parse_board(board, player)?;
+    let mut engine = AlphaBeta::new(ThreatEvaluator::default(), DefaultClock::new(), 1 << 16);
+
+    return engine.choose_move(&board, Budget::Milliseconds(time_ms));
+}
+
+/// Best move of the exact solver, the first one from the center if several are equally good. Positions with few tokens can
+/// take minutes to solve.
+#[wasm_bindgen]
+pub fn predict_best_move_perfect(board: &str, player: i8) -> Option<usize> {
+    let board = parse_board(board, player)?;
+
+    return Solver::<6,7,4>::new(1 << 20).best_move(&board);
+}
+
+/// Searches the position and returns the `SearchReport` serialized as JSON
+#[wasm_bindgen]
+pub fn analyze(board: &str, player: i8, iters: usize) -> Option<String> {
+    let board = parse_board(board, player)?;
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    let mut tree = Tree::new(board);
+    let iterations = tree.predict(iters, 1, &config, &mut rand::thread_rng());
+
+    return Some(SearchReport::from_tree(&tree, iterations, config.clamped_draw_value()).to_json());
+}
+
+/// Keeps the search tree between moves, so that the statistics gathered for the position actually played are reused.
+/// The search can also run in chunks with `start`, `step`, `peek_best_move` and `stop`, so that it does not block the page
+/// and can continue while the opponent thinks.
+#[wasm_bindgen]
+pub struct Searcher {
+    tree: Tree<Connect4<6,7,4>>,
+    config: SearchConfig<Selection, Rollout, ThreatEvaluator>,
+    move_selection: MoveSelection,
+    iterations: usize,
+    iterations_saved: usize,
+    searching: bool,
+    rng: StdRng
+}
+
+#[wasm_bindgen]
+impl Searcher {
+    pub fn from_board(board: &str, player: i8) -> Option<Searcher> {
+        let board = parse_board(board, player)?;
+
+        return Some(Searcher { tree: Tree::new(board), config: SearchConfig::default(), move_selection: MoveSelection::default(), iterations: 0, iterations_saved: 0, searching: false, rng: StdRng::from_entropy() });
+    }
+
+    /// takes one of "ucb1", "ucb1-tuned", "kl-ucb", "thompson", "rave", "puct", returns false if the name is not known
+    pub fn set_selection_policy(&mut self, name: &str) -> bool {
+        if let Some(policy) = Selection::from_name(name) {
+            self.config.selection = policy;
+            return true;
+        }
+
+        return false;
+    }
+
+    /// selects RAVE with the given schedule, see `RaveSchedule::from_name`, returns false if the name is not known
+    pub fn set_rave_schedule(&mut self, name: &str, parameter: f32) -> bool {
+        if let Some(schedule) = RaveSchedule::from_name(name, parameter) {
+            self.config.selection = Selection::Rave(Rave { schedule, ..Rave::default() });
+            return true;
+        }
+
+        return false;
+    }
+
+    /// takes one of "uniform", "tactical", "weighted", "softmax", returns false if the name is not known
+    pub fn set_rollout_policy(&mut self, name: &str) -> bool {
+        if let Some(policy) = Rollout::from_name(name) {
+            self.config.rollout = policy;
+            return true;
+        }
+
+        return false;
+    }
+
+    /// sets the reward of a draw for the player to move, between -1 (as bad as a loss) and 1 (as good as a win), see `SearchConfig::draw_value`.
+    /// Values outside of the range are clamped, NaN counts as 0
+    pub fn set_draw_value(&mut self, draw_value: f32) {
+        self.config.draw_value = clamp_draw_value(draw_value);
+    }
+
+    /// reseeds the random number generator, so that the following searches are reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// stops rollouts after `cutoff` plies and values the position reached by `ThreatEvaluator`, blended with the evaluation
+    /// of the leaf by `weight` between 0 and 1. `None` and 0 play rollouts to the end, 1 replaces them by the evaluation
+    pub fn set_leaf_evaluation(&mut self, cutoff: Option<usize>, weight: f32) {
+        self.config.leaf = LeafEvaluation { evaluator: ThreatEvaluator::default(), cutoff, weight };
+    }
+
+    /// sets the exploration constant of UCB1
+    pub fn set_ucb1_constant(&mut self, constant: f32) {
+        self.config.selection = Selection::Ucb1(Ucb1 { constant });
+    }
+
+    /// takes one of "max-visits", "max-value", "robust-max", "secure-child", "sample", see `MoveSelection::from_name`,
+    /// returns false if the name is not known or the temperature of "sample" is not positive and finite
+    pub fn set_move_selection(&mut self, name: &str, parameter: f32) -> bool {
+        if let Some(move_selection) = MoveSelection::from_name(name, parameter) {
+            self.move_selection = move_selection;
+            return true;
+        }
+
+        return false;
+    }
+
+    /// stops `predict_best_move` once the most visited move leads by more visits than the remaining iterations can add
+    /// if `visit_gap` is true, and once every move but one is proven to lose if `proven` is true
+    pub fn set_early_stop(&mut self, visit_gap: bool, proven: bool) {
+        self.config.early_stop = EarlyStop { visit_gap, proven };
+    }
+
+    /// limits the number of nodes of the search tree, once it is reached the least visited subtrees are pruned
+    /// if `prune` is true, otherwise the tree stops growing. 0 removes the limit
+    pub fn set_max_nodes(&mut self, max_nodes: usize, prune: bool) {
+        let node_limit = match (max_nodes, prune) {
+            (0, _) => None,
+            (_, true) => Some(NodeLimit::Prune(max_nodes)),
+            (_, false) => Some(NodeLimit::StopExpansion(max_nodes))
+        };
+        self.tree.set_node_limit(node_limit);
+    }
+
+    /// moves the root to the position after `column` was played, returns false if the move is not available
+    pub fn play(&mut self, column: usize) -> bool {
+        return self.tree.advance(column);
+    }
+
+    pub fn predict_best_move(&mut self, iters: usize) -> Option<usize> {
+        self.iterations = self.tree.predict(iters, 1, &self.config, &mut self.rng);
+        self.iterations_saved = iters - self.iterations;
+
+        return self.best_move();
+    }
+
+    pub fn predict_best_move_timed(&mut self, time_ms: f64) -> Option<usize> {
+        self.iterations = self.tree.predict_for(time_ms, 1, &self.config, &mut DefaultClock::new(), &mut self.rng);
+        self.iterations_saved = 0;
+
+        return self.best_move();
+    }
+
+    /// starts a new search of `board` with `player` to move, dropping the current tree but keeping the settings.
+    /// Returns false if the board is not valid or it is not the turn of `player`, in which case nothing changes
+    pub fn start(&mut self, board: &str, player: i8) -> bool {
+        if let Some(board) = parse_board(board, player) {
+            let node_limit = self.tree.node_limit();
+            self.tree = Tree::new(board);
+            self.tree.set_node_limit(node_limit);
+            self.iterations = 0;
+            self.iterations_saved = 0;
+            self.searching = true;
+            return true;
+        }
+
+        return false;
+    }
+
+    /// continues searching the current root while keeping the tree, e.g. to ponder on the position after `play`
+    pub fn resume(&mut self) {
+        self.iterations = 0;
+        self.searching = true;
+    }
+
+    /// runs up to `iters` more iterations of the search started by `start`, the early stop applies to these iterations only.
+    /// Returns the number of iterations performed, 0 once the root is proven or the search was stopped
+    pub fn step(&mut self, iters: usize) -> usize {
+        if ! self.searching {
+            return 0;
+        }
+
+        let performed = self.tree.predict(iters, 1, &self.config, &mut self.rng);
+        self.iterations += performed;
+        return performed;
+    }
+
+    /// the most visited move so far, the search is not changed
+    pub fn peek_best_move(&self) -> Option<usize> {
+        return self.tree.get_most_visited_child(Tree::<Connect4<6,7,4>>::ROOT).map(|child| self.tree.node(child).move_index);
+    }
+
+    pub fn is_searching(&self) -> bool {
+        return self.searching;
+    }
+
+    /// ends the search and returns the move picked by the move selection, the tree is kept so that `play` can reuse it
+    pub fn stop(&mut self) -> Option<usize> {
+        self.searching = false;
+        return self.best_move();
+    }
+
+    /// number of iterations which went through the current root
+    pub fn visits(&self) -> usize {
+        return self.tree.root().stats.visits;
+    }
+
+    /// iterations of the last `predict_best_move` which were not needed, because the best move was decided or proven earlier
+    pub fn iterations_saved(&self) -> usize {
+        return self.iterations_saved;
+    }
+
+    /// bytes allocated by the search tree
+    pub fn memory_usage(&self) -> usize {
+        return self.tree.memory_usage();
+    }
+
+    /// `SearchReport` of the current root serialized as JSON, the iterations are those of the last search, or of all steps since `start`
+    pub fn report(&self) -> String {
+        return SearchReport::from_tree(&self.tree, self.iterations, self.config.clamped_draw_value()).to_json();
+    }
+}
+
+impl Searcher {
+    /// picks the move with the move selection, counting the iterations it adds to the search
+    fn best_move(&mut self) -> Option<usize> {
+        let visits = self.tree.root().stats.visits;
+        let child = self.tree.choose_move(&self.move_selection, 1, &self.config, &mut self.rng);
+        self.iterations += self.tree.root().stats.visits - visits;
+
+        return child.map(|child| self.tree.node(child).move_index);
+    }
+}
+
+/// "ongoing", "draw", "x" or "o" for the winner, `None` if the board is invalid
+#[wasm_bindgen]
+pub fn get_score(board: &str) -> Option<String> {
+    let board = Connect4::<6,7,4>::from_string(board)?;
+
+    let outcome = match board.get_outcome() {
+        Outcome::Ongoing => "ongoing",
+        Outcome::Draw => "draw",
+        Outcome::Win(1) => "x",
+        Outcome::Win(_) => "o"
+    };
+
+    return Some(outcome.to_string());
+}
+
 
 #[cfg(test)]
 mod tictactoe;
 
 #[cfg(test)]
 mod tests {
-    use crate::predict_best_move;
+    use crate::{analyze, get_score, opening_book, predict_best_move, predict_best_move_alpha_beta, predict_best_move_perfect, predict_best_move_seeded, predict_best_move_timed, search_best_move_for, Searcher};
+    use crate::clock::FakeClock;
+    use rand::{SeedableRng, rngs::StdRng};
 
     #[test]
     fn best_move_test() {
@@ -53,8 +372,10 @@ mod tests {
                             ......O\n\
                             .OXX.XO\n\
                         ";
-        assert_eq!(predict_best_move(board, 1, 100), Some(4));
-        assert_eq!(predict_best_move(board, -1, 100), Some(4));
+        assert_eq!(predict_best_move_seeded(board, 1, 100, 0), Some(4));
+        // X is to move
+        assert_eq!(predict_best_move_seeded(board, -1, 100, 0), None);
+        assert_eq!(predict_best_move_seeded(&board.replacen(".......\n......O", "......X\n......O", 1), -1, 100, 0), Some(4));
 
         let board = "\
                             .......\n\
@@ -64,6 +385,337 @@ mod tests {
                             O.....X\n\
                             O.....X\n\
                         ";
+        assert_eq!(predict_best_move_seeded(board, 1, 100, 0), Some(6));
         assert_eq!(predict_best_move(board, 1, 100), Some(6));
     }
+
+    #[test]
+    fn opening_book_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                        ";
+        assert!(opening_book().len() > 1);
+        assert_eq!(predict_best_move(board, 1, 0), Some(3));
+        assert_eq!(predict_best_move_seeded(board, 1, 0, 0), Some(3));
+
+        // mirror images share the entry
+        let left = board.rsplit_once(".......").map(|(rest, end)| format!("{}..X....{}", rest, end)).unwrap();
+        let right = board.rsplit_once(".......").map(|(rest, end)| format!("{}....X..{}", rest, end)).unwrap();
+        assert_eq!(predict_best_move(&left, -1, 0).map(|col| 6 - col), predict_best_move(&right, -1, 0));
+    }
+
+    #[test]
+    fn get_score_test() {
+        assert_eq!(get_score(".......\n.......\n.......\n.......\n.......\n.......\n"), Some("ongoing".to_string()));
+        assert_eq!(get_score(".......\n.......\nX......\nXO.....\nXO.....\nXO.....\n"), Some("x".to_string()));
+        assert_eq!(get_score("XOXOXOX\nXOXOXOX\nOXOXOXO\nOXOXOXO\nXOXOXOX\nOXOXOXO\n"), Some("draw".to_string()));
+        assert_eq!(get_score(".......\n.......\n.......\n.......\n.......\n.OO....\n"), None);
+    }
+
+    #[test]
+    fn seeded_search_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            ...X...\n\
+                        ";
+        let mut first = Searcher::from_board(board, -1).unwrap();
+        let mut second = Searcher::from_board(board, -1).unwrap();
+        first.set_seed(42);
+        second.set_seed(42);
+
+        assert_eq!(first.predict_best_move(500), second.predict_best_move(500));
+        assert_eq!(first.tree, second.tree);
+
+        let moves: Vec<Option<usize>> = (0..5).map(|_| predict_best_move_seeded(board, -1, 50, 7)).collect();
+        assert!(moves.iter().all(|&m| m == moves[0]));
+    }
+
+    #[test]
+    fn best_move_timed_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            O.....X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        // the fake clock advances by 0.1 ms every time it is read
+        let clock = || FakeClock { time: 0., step: 0.1 };
+        assert_eq!(search_best_move_for(board, 1, 50., &mut clock(), &mut StdRng::seed_from_u64(0)), Some(6));
+        assert_eq!(search_best_move_for("invalid", 1, 50., &mut clock(), &mut StdRng::seed_from_u64(0)), None);
+        assert_eq!(predict_best_move_timed("invalid", 1, 50.), None);
+    }
+
+    #[test]
+    fn best_move_alpha_beta_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            O.....X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        assert_eq!(predict_best_move_alpha_beta(board, 1, 50.), Some(6));
+        assert_eq!(predict_best_move_alpha_beta(board, -1, 50.), None);
+
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            O.....X\n\
+                            O.....X\n\
+                            O..X..X\n\
+                        ";
+        assert_eq!(predict_best_move_alpha_beta(board, -1, 50.), Some(0));
+        assert_eq!(predict_best_move_alpha_beta("invalid", 1, 50.), None);
+    }
+
+    #[test]
+    fn best_move_perfect_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            O..O...\n\
+                            O..XXX.\n\
+                        ";
+        // X wins in columns 2 and 6, the one closer to the center is played
+        assert_eq!(predict_best_move_perfect(board, 1), Some(2));
+        assert_eq!(predict_best_move_perfect(board, -1), None);
+        assert_eq!(predict_best_move_perfect("invalid", 1), None);
+    }
+
+    #[test]
+    fn searcher_reuse_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                        ";
+        let mut searcher = Searcher::from_board(board, 1).unwrap();
+        let best = searcher.predict_best_move(100).unwrap();
+        assert_eq!(searcher.visits(), 100);
+
+        // statistics of the chosen subtree are kept
+        assert!(searcher.play(best));
+        assert!(searcher.visits() > 0);
+        assert!(! searcher.play(7));
+
+        let report = searcher.report();
+        assert!(report.starts_with("{\"iterations\":100,"));
+
+        assert!(report.contains("\"memory_bytes\":"));
+
+        assert!(Searcher::from_board("invalid", 1).is_none());
+    }
+
+    #[test]
+    fn searcher_policy_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            O.....X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        for policy in ["ucb1", "ucb1-tuned", "kl-ucb", "thompson", "rave", "puct"] {
+            let mut searcher = Searcher::from_board(board, 1).unwrap();
+            assert!(searcher.set_selection_policy(policy));
+            assert_eq!(searcher.predict_best_move(200), Some(6), "{}", policy);
+        }
+
+        let mut searcher = Searcher::from_board(board, 1).unwrap();
+        assert!(! searcher.set_selection_policy("unknown"));
+        searcher.set_ucb1_constant(1.);
+        assert_eq!(searcher.predict_best_move(200), Some(6));
+
+        for policy in ["uniform", "tactical", "weighted", "softmax"] {
+            let mut searcher = Searcher::from_board(board, 1).unwrap();
+            assert!(searcher.set_rollout_policy(policy));
+            assert_eq!(searcher.predict_best_move(200), Some(6), "{}", policy);
+        }
+        assert!(! searcher.set_rollout_policy("unknown"));
+
+        assert!(searcher.set_rave_schedule("minimum-mse", 0.1));
+        assert_eq!(searcher.predict_best_move(200), Some(6));
+        assert!(! searcher.set_rave_schedule("unknown", 0.1));
+
+        searcher.set_draw_value(-0.5);
+        assert_eq!(searcher.predict_best_move(200), Some(6));
+
+        for (cutoff, weight) in [(Some(4), 0.), (None, 1.), (Some(2), 0.5)] {
+            let mut searcher = Searcher::from_board(board, 1).unwrap();
+            searcher.set_leaf_evaluation(cutoff, weight);
+            assert_eq!(searcher.predict_best_move(200), Some(6), "{:?} {}", cutoff, weight);
+        }
+    }
+
+    #[test]
+    fn searcher_draw_value_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            ......X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        for draw_value in [-3., 3., f32::NAN] {
+            let mut searcher = Searcher::from_board(board, -1).unwrap();
+            assert!(searcher.set_selection_policy("thompson"));
+            searcher.set_draw_value(draw_value);
+            assert_eq!(searcher.predict_best_move(1000), Some(6), "{}", draw_value);
+        }
+    }
+
+    #[test]
+    fn searcher_early_stop_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            ......X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        let mut searcher = Searcher::from_board(board, -1).unwrap();
+        searcher.set_seed(0);
+        assert_eq!(searcher.predict_best_move(5000), Some(6));
+        assert_eq!(searcher.iterations_saved(), 0);
+
+        let mut searcher = Searcher::from_board(board, -1).unwrap();
+        searcher.set_seed(0);
+        searcher.set_early_stop(true, true);
+        assert_eq!(searcher.predict_best_move(5000), Some(6));
+        assert!(searcher.iterations_saved() > 0);
+        assert_eq!(searcher.visits() + searcher.iterations_saved(), 5000);
+    }
+
+    #[test]
+    fn searcher_move_selection_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            ......X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        for (name, parameter) in [("max-visits", 0.), ("max-value", 0.), ("robust-max", 1000.), ("secure-child", 1.), ("sample", 0.1)] {
+            let mut searcher = Searcher::from_board(board, -1).unwrap();
+            assert!(searcher.set_move_selection(name, parameter));
+            assert_eq!(searcher.predict_best_move(1000), Some(6), "{}", name);
+        }
+
+        let mut searcher = Searcher::from_board(board, -1).unwrap();
+        assert!(! searcher.set_move_selection("unknown", 0.));
+        assert!(! searcher.set_move_selection("sample", f32::NAN));
+        assert!(! searcher.set_move_selection("sample", -1.));
+    }
+
+    #[test]
+    fn searcher_incremental_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                        ";
+        let mut searcher = Searcher::from_board(board, 1).unwrap();
+        assert_eq!(searcher.step(100), 0);
+        assert!(! searcher.start("invalid", 1));
+        assert!(! searcher.is_searching());
+
+        searcher.set_seed(5);
+        assert!(searcher.start(board, 1));
+        assert!(searcher.is_searching());
+        assert_eq!(searcher.peek_best_move(), None);
+        for _ in 0..3 {
+            assert_eq!(searcher.step(100), 100);
+            assert!(searcher.peek_best_move().is_some());
+        }
+        assert_eq!(searcher.visits(), 300);
+        assert!(searcher.report().starts_with("{\"iterations\":300,"));
+
+        // the search in chunks is the same as in one go
+        let mut one_go = Searcher::from_board(board, 1).unwrap();
+        one_go.set_seed(5);
+        assert_eq!(one_go.predict_best_move(300), searcher.peek_best_move());
+        assert_eq!(one_go.tree, searcher.tree);
+
+        let best = searcher.stop();
+        assert_eq!(best, searcher.peek_best_move());
+        assert!(! searcher.is_searching());
+        assert_eq!(searcher.step(100), 0);
+        assert_eq!(searcher.visits(), 300);
+
+        // pondering on the position after the move, then starting a new game
+        assert!(searcher.play(best.unwrap()));
+        let visits = searcher.visits();
+        searcher.resume();
+        assert_eq!(searcher.step(100), 100);
+        assert_eq!(searcher.visits(), visits + 100);
+        assert!(searcher.start(board, 1));
+        assert_eq!(searcher.visits(), 0);
+    }
+
+    #[test]
+    fn searcher_max_nodes_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                        ";
+        for prune in [false, true] {
+            let mut searcher = Searcher::from_board(board, 1).unwrap();
+            searcher.set_max_nodes(100, prune);
+            searcher.predict_best_move(1000).unwrap();
+            assert_eq!(searcher.visits(), 1000);
+            assert!(searcher.memory_usage() < 100 * 100);
+            assert!(searcher.report().contains("\"node_count\":") );
+        }
+
+        let mut searcher = Searcher::from_board(board, 1).unwrap();
+        searcher.set_max_nodes(100, false);
+        searcher.set_max_nodes(0, false);
+        searcher.predict_best_move(1000).unwrap();
+        assert!(searcher.memory_usage() > 100 * 100);
+    }
+
+    #[test]
+    fn analyze_test() {
+        let board = "\
+                            .......\n\
+                            .......\n\
+                            .......\n\
+                            O.....X\n\
+                            O.....X\n\
+                            O.....X\n\
+                        ";
+        let report = analyze(board, 1, 100).unwrap();
+        assert!(report.contains("\"principal_variation\":[\"6\"]"));
+        assert!(report.contains("{\"move\":\"6\","));
+        assert_eq!(analyze("invalid", 1, 100), None);
+    }
 }
\ No newline at end of file
@@ -1,15 +1,321 @@
 use core::panic;
+use std::fmt;
+use std::str::FromStr;
 
 use rand::prelude::IteratorRandom;
-use rand::seq::SliceRandom;
-use rand::rngs::ThreadRng;
+use rand::Rng;
+
+use super::clock::Clock;
+use super::evaluation::{evaluate_expansion, simulate, Evaluator, LeafEvaluation, NoEvaluator};
+use super::rollout::RolloutPolicy;
+use super::selection::SelectionPolicy;
+
+/// State of a game, a win carries the winner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Ongoing,
+    Win(i8),
+    Draw
+}
+
+impl Outcome {
+    pub fn is_over(&self) -> bool {
+        return *self != Outcome::Ongoing;
+    }
+
+    /// 1 or -1, 0 for a draw or a game which is not over
+    pub fn winner(&self) -> i8 {
+        return match *self {
+            Outcome::Win(player) => player,
+            _ => 0
+        };
+    }
+}
 
 pub trait GeneralGame : Clone {
-    fn update(&mut self, index:usize, player:i8);
-    fn get_score(&self) -> i8;
-    fn get_available(&self) -> Vec<usize>;
+    /// A move of the game, written and parsed in the notation of the game. The default move stands in for the move
+    /// leading to the root of a search.
+    type Move: Copy + Eq + fmt::Debug + fmt::Display + FromStr + Default;
+
+    /// plays `index` for the player to move and passes the turn
+    fn update(&mut self, index: Self::Move);
+    /// takes back the last move and returns it, `None` if no move was played since the game was created
+    fn undo(&mut self) -> Option<Self::Move>;
+    /// `None` if no move was played since the game was created
+    fn last_move(&self) -> Option<Self::Move>;
+    /// the player to move, 1 or -1
+    fn player(&self) -> i8;
+    fn get_outcome(&self) -> Outcome;
+    fn get_available(&self) -> Vec<Self::Move>;
+
+    /// moves with which `player` wins immediately, also when it is not the turn of `player`
+    fn winning_moves(&self, player: i8) -> Vec<Self::Move>;
+
+    /// static preference of a move, must be positive, used by `WeightedRollout`
+    fn move_weight(&self, _index: Self::Move, _player: i8) -> f32 {
+        return 1.;
+    }
+
+    /// estimate of how good a move is for `player`, higher is better, used by `SoftmaxRollout`
+    fn move_heuristic(&self, _index: Self::Move, _player: i8) -> f32 {
+        return 0.;
+    }
+
+    /// the move written as by its `Display`, `None` if it can not be parsed or is not available
+    fn parse_move(&self, text: &str) -> Option<Self::Move> {
+        let index = text.trim().parse().ok()?;
+        return if self.get_available().contains(&index) { Some(index) } else { None };
+    }
+}
+
+/// Policies used by the search
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchConfig<S: SelectionPolicy, R: RolloutPolicy, E = NoEvaluator> {
+    pub selection: S,
+    pub rollout: R,
+    /// how new leaves are valued, by rollouts to the end of the game unless configured otherwise
+    pub leaf: LeafEvaluation<E>,
+    /// Reward of a draw for the player to move at the root, between -1 (as bad as a loss) and 1 (as good as a win), 0 by
+    /// default. The opponent values a draw as `-draw_value`, so negative values avoid draws and expect the opponent to seek them.
+    /// The search uses `clamped_draw_value`, so values outside of the range are clamped and NaN counts as 0.
+    pub draw_value: f32,
+    /// ends `Node::predict` and `Tree::predict` before all iterations are used, disabled by default
+    pub early_stop: EarlyStop
+}
+
+impl<S: SelectionPolicy, R: RolloutPolicy, E> SearchConfig<S, R, E> {
+    /// `draw_value` clamped to between -1 and 1, 0 if it is NaN
+    pub fn clamped_draw_value(&self) -> f32 {
+        return clamp_draw_value(self.draw_value);
+    }
+}
+
+/// clamps the reward of a draw to between -1 and 1, NaN counts as 0
+pub fn clamp_draw_value(draw_value: f32) -> f32 {
+    if draw_value.is_nan() {
+        return 0.;
+    }
+
+    return draw_value.clamp(-1., 1.);
+}
+
+/// Rules which end the search early once the move it would play can not change any more
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EarlyStop {
+    /// stop once the most visited move leads the runner-up by more visits than the remaining iterations can add
+    pub visit_gap: bool,
+    /// stop once every move but one is proven to lose
+    pub proven: bool
+}
+
+impl EarlyStop {
+    /// whether the search can stop given the statistics and proven values of the children of the root, and the visits the
+    /// remaining iterations would add
+    pub(crate) fn can_stop<I: Iterator<Item = (Stats, Proven)> + Clone>(&self, children: I, remaining_visits: usize) -> bool {
+        if ! self.visit_gap && ! self.proven {
+            return false;
+        }
+
+        let candidates = children.filter(|(_, proven)| *proven != Proven::Loss);
+        if self.proven && candidates.clone().count() == 1 {
+            return true;
+        }
+
+        if self.visit_gap {
+            let (mut first, mut second) = (0usize, 0usize);
+            for (stats, _) in candidates {
+                if stats.visits > first {
+                    second = first;
+                    first = stats.visits;
+                }
+                else if stats.visits > second {
+                    second = stats.visits;
+                }
+            }
+
+            return first - second > remaining_visits;
+        }
+
+        return false;
+    }
+}
+
+/// Game-theoretic value of a node, from the point of view of the player who made the move leading to it
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Proven {
+    Unknown,
+    Win,
+    Loss,
+    Draw
+}
+
+/// Statistics of a node used by the selection policies, from the point of view of the player who made the move leading to it
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Stats {
+    pub visits: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub amaf_visits: usize,
+    pub amaf_wins: usize,
+    pub amaf_losses: usize,
+    pub amaf_draws: usize,
+    /// prior probability of the move leading to the node, uniform unless the evaluator gives a policy
+    pub prior: f32
+}
+
+impl Stats {
+    /// average reward, where a draw is worth `draw_value`
+    pub fn value(&self, draw_value: f32) -> f32 {
+        if self.visits == 0 {
+            return 0.;
+        }
+
+        return (self.wins as f32 - self.losses as f32 + draw_value * self.draws as f32) / self.visits as f32;
+    }
+
+    /// average all-moves-as-first reward, where a draw is worth `draw_value`
+    pub fn amaf_value(&self, draw_value: f32) -> f32 {
+        if self.amaf_visits == 0 {
+            return 0.;
+        }
+
+        return (self.amaf_wins as f32 - self.amaf_losses as f32 + draw_value * self.amaf_draws as f32) / self.amaf_visits as f32;
+    }
+}
+
+/// Plays a rollout of at most `max_plies` plies on `game` with `player` to move, recording the moves played into `moves`.
+/// The moves are taken back before returning, so `game` is left as it was.
+/// Returns the winner, or 0 for a draw, if the game ended, otherwise `evaluate` of the position reached and the player to move.
+pub(crate) fn truncated_playout<T, R, G, V>(game: &mut T, player: i8, policy: &R, rng: &mut G, mut moves: Option<&mut Vec<(T::Move, i8)>>, max_plies: usize, evaluate: impl FnOnce(&T, i8) -> V) -> Result<i8, V>
+where T: GeneralGame, R: RolloutPolicy, G: Rng + ?Sized {
+    let mut current_player = player;
+    let mut plies = 0;
+
+    let result = loop {
+        if plies == max_plies {
+            break Err(evaluate(game, current_player));
+        }
+
+        let outcome = game.get_outcome();
+        if outcome.is_over() {
+            break Ok(outcome.winner());
+        }
+
+        let available = game.get_available();
+        let index = policy.choose(game, current_player, &available, rng);
+        if let Some(moves) = moves.as_mut() {
+            moves.push((index, current_player));
+        }
+        game.update(index);
+        current_player *= -1;
+        plies += 1;
+    };
+
+    for _ in 0..plies {
+        game.undo();
+    }
+
+    return result;
+}
+
+/// Plays a game on `game` with `player` to move until it ends, recording the moves played into `moves`.
+/// The moves are taken back before returning. Returns the winner, or 0 for a draw.
+pub(crate) fn playout<T: GeneralGame, R: RolloutPolicy, G: Rng + ?Sized>(game: &mut T, player: i8, policy: &R, rng: &mut G, moves: Option<&mut Vec<(T::Move, i8)>>) -> i8 {
+    return match truncated_playout(game, player, policy, rng, moves, usize::MAX, |_, _| ()) {
+        Ok(winner) => winner,
+        Err(()) => panic!("The game did not end")
+    };
+}
+
+/// value of a position which was not expanded yet with `player` to move, `Proven::Unknown` unless the game has ended
+pub(crate) fn terminal_value<T: GeneralGame>(game: &T, player: i8) -> Proven {
+    return match game.get_outcome() {
+        Outcome::Win(winner) if winner == player => Proven::Loss,
+        Outcome::Win(_) => Proven::Win,
+        Outcome::Draw => Proven::Draw,
+        Outcome::Ongoing => Proven::Unknown
+    };
+}
+
+/// backs up the proven values of the children of a node, `Proven::Unknown` if the value of the node can not be decided yet
+pub(crate) fn backup_proven<I: Iterator<Item = Proven> + Clone>(children: I) -> Proven {
+    if children.clone().next().is_none() {
+        return Proven::Unknown;
+    }
+
+    if children.clone().any(|proven| proven == Proven::Win) {
+        return Proven::Loss;
+    }
+    else if children.clone().all(|proven| proven == Proven::Loss) {
+        return Proven::Win;
+    }
+    else if children.clone().all(|proven| proven != Proven::Unknown) {
+        return Proven::Draw;
+    }
+
+    return Proven::Unknown;
 }
 
+/// Picks the child to descend into given the statistics and proven values of the children. Proven wins are always picked,
+/// proven losses never, and children which were not visited yet are picked first in random order unless the policy scores
+/// them by their priors.
+pub(crate) fn select_child<P: SelectionPolicy, I: Iterator<Item = (Stats, Proven)> + Clone, G: Rng + ?Sized>(children: I, parent_visits: usize, policy: &P, draw_value: f32, rng: &mut G) -> Option<usize> {
+    // a proven win is always played
+    if let Some(index) = children.clone().position(|(_, proven)| proven == Proven::Win) {
+        return Some(index);
+    }
+
+    let mut max_score = f32::NEG_INFINITY;
+    let mut max_index = None;
+
+    for (index, (stats, proven)) in children.clone().enumerate(){
+        // proven losses are never played
+        if proven == Proven::Loss {
+            continue;
+        }
+
+        let score = if stats.visits == 0 && ! policy.uses_priors() { f32::INFINITY } else { policy.score(&stats, parent_visits, draw_value, rng) };
+        if max_index.is_none() || score > max_score {
+            max_index = Some(index);
+            max_score = score;
+        }
+    }
+
+    // if some nodes were not visited yet, select random
+    if max_score == f32::INFINITY {
+        let not_visited = children.enumerate().filter_map(|(i, (stats, proven))| if stats.visits == 0 && proven != Proven::Loss {Some(i)} else {None});
+        return not_visited.choose(rng);
+    }
+
+    return max_index;
+}
+
+/// Picks the move to play given the statistics and proven values of the children, the one with most visits except that
+/// a proven win is always picked and proven losses only if every move loses
+pub(crate) fn most_visited_child<I: Iterator<Item = (Stats, Proven)> + Clone>(children: I) -> Option<usize> {
+    if let Some(index) = children.clone().position(|(_, proven)| proven == Proven::Win) {
+        return Some(index);
+    }
+    let all_lose = children.clone().all(|(_, proven)| proven == Proven::Loss);
+
+    let mut most_visits = 0;
+    let mut most_visited = None;
+
+    for (index, (stats, proven)) in children.enumerate() {
+        if proven == Proven::Loss && ! all_lose {
+            continue;
+        }
+        if stats.visits > most_visits {
+            most_visits = stats.visits;
+            most_visited = Some(index);
+        }
+    }
+
+    return most_visited;
+}
+
+/// Search tree where every node owns its children and a copy of the game, `Tree` is the faster arena-allocated equivalent
 #[derive(Debug,PartialEq, Clone)]
 pub struct Node<T:GeneralGame> {
     pub game: T,
@@ -17,125 +323,135 @@ pub struct Node<T:GeneralGame> {
     pub visits: usize,
     pub wins: usize,
     pub losses: usize,
+    pub draws: usize,
+    /// all-moves-as-first statistics of the move leading to this node, collected in the simulations of the parent
+    pub amaf_visits: usize,
+    pub amaf_wins: usize,
+    pub amaf_losses: usize,
+    pub amaf_draws: usize,
     pub children: Vec<Node<T>>,
     created_children: bool,
-    pub move_index: usize
+    pub move_index: T::Move,
+    pub proven: Proven,
+    /// prior probability of `move_index`, see `Stats::prior`
+    pub prior: f32
 }
 
 impl<T:GeneralGame> Node<T> {
-    pub fn new(game : T, player: i8, move_index : usize) -> Node<T>{
-        return Node {game, player: player, visits: 0, wins: 0, losses: 0, children: Vec::new(), created_children: false, move_index: move_index};
+    pub fn new(game : T, move_index : T::Move) -> Node<T>{
+        return Node {player: game.player(), game, visits: 0, wins: 0, losses: 0, draws: 0, amaf_visits: 0, amaf_wins: 0, amaf_losses: 0, amaf_draws: 0, children: Vec::new(), created_children: false, move_index, proven: Proven::Unknown, prior: 0.};
     }
 
-    pub fn rollout(&self, rng: &mut ThreadRng) -> i8 {
-        let mut current_game = self.game.clone();
-        let mut current_player = self.player;
-
-        loop {
-            let score = current_game.get_score();
-            if score != 0 {
-                return score;
-            }
-
-            let available = current_game.get_available();
-
-            if available.len() == 0 {
-                return 0;
-            }
-
-            let index = *available.choose(rng).unwrap();
-            current_game.update(index, current_player);
-            current_player *= -1;
-        }
+    pub fn rollout<R: RolloutPolicy, G: Rng + ?Sized>(&self, policy: &R, rng: &mut G) -> i8 {
+        return playout(&mut self.game.clone(), self.player, policy, rng, None);
     }
 
     pub fn create_children(&mut self){
         self.created_children = true;
 
-        // If someone already won, there is no point in creating children
-        if self.game.get_score() != 0{
+        // If the game is over, there is no point in creating children
+        if self.game.get_outcome().is_over() {
             return;
         }
 
 
         let available = self.game.get_available();
+        let prior = 1. / available.len() as f32;
 
         for index in available{
-            let mut child = Node::new(self.game.clone(), -self.player, index);
-            child.game.update(index, self.player);
+            let mut game = self.game.clone();
+            game.update(index);
+            let mut child = Node::new(game, index);
+            child.prior = prior;
             self.children.push(child);
         }
     }
 
-    pub fn get_score(&self, parent_visits: usize) -> f32 {
-        const UPPER_BOUND_CONSTANT : f32 = 1.4142*2.;
+    /// Creates the children, with the priors given by the evaluator of `config` if the selection policy uses them.
+    /// Returns the evaluation of this node which comes with the priors.
+    fn expand<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>>(&mut self, config: &SearchConfig<S, R, E>) -> Option<f32> {
+        self.create_children();
+        if self.children.is_empty() {
+            return None;
+        }
 
-        if self.visits == 0 {
-            return f32::INFINITY;
+        let (priors, value) = evaluate_expansion(&self.game, self.player, config)?;
+        if let Some(priors) = priors {
+            for (child, prior) in self.children.iter_mut().zip(priors) {
+                child.prior = prior;
+            }
         }
 
-        let fwins = (self.wins as f32)  - (self.losses as f32);
-        let fvisits = self.visits as f32;
-        let fparent_visits = parent_visits as f32;
+        return Some(value);
+    }
 
-        return (fwins)/(fvisits) + UPPER_BOUND_CONSTANT * (fparent_visits.ln() / fvisits).sqrt();
+    pub fn stats(&self) -> Stats {
+        return Stats {
+            visits: self.visits, wins: self.wins, losses: self.losses, draws: self.draws,
+            amaf_visits: self.amaf_visits, amaf_wins: self.amaf_wins, amaf_losses: self.amaf_losses, amaf_draws: self.amaf_draws,
+            prior: self.prior
+        };
     }
 
-    pub fn get_child_with_highest_score(&self, rng: &mut ThreadRng) -> Option<usize> {
-        if self.children.len() == 0{
-            return None;
-        }
-        let mut max_score = f32::NEG_INFINITY;
-        let mut max_index = 0usize;
+    /// average reward of the node, where a draw is worth `draw_value`
+    pub fn value(&self, draw_value: f32) -> f32 {
+        return self.stats().value(draw_value);
+    }
 
-        for (index, node) in self.children.iter().enumerate(){
-            let score = node.get_score(self.visits);
-            if score > max_score {
-                max_index = index;
-                max_score = score;
-            }
-        }
+    /// average all-moves-as-first reward of the move leading to this node, where a draw is worth `draw_value`
+    pub fn amaf_value(&self, draw_value: f32) -> f32 {
+        return self.stats().amaf_value(draw_value);
+    }
 
-        // if some nodes were not visited yet, select random
-        if max_score == f32::INFINITY {
-            let not_visited = self.children.iter().enumerate().filter_map(|(i, n)| if ! n.created_children {Some(i)} else {None});
-            return not_visited.choose(rng);
+    pub fn get_child_with_highest_score<P: SelectionPolicy, G: Rng + ?Sized>(&self, policy: &P, draw_value: f32, rng: &mut G) -> Option<usize> {
+        if self.children.is_empty() {
+            return None;
         }
 
-        return Some(max_index);
+        return select_child(self.children.iter().map(|child| (child.stats(), child.proven)), self.visits, policy, draw_value, rng);
+    }
+
+    pub fn propagate<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&mut self, rollouts: usize, config: &SearchConfig<S, R, E>, rng: &mut G) -> (usize, usize, usize){
+        // returns (player1 wins, player-1 wins, draws)
+        return self.propagate_recording(rollouts, config, config.clamped_draw_value(), rng, &mut Vec::new());
     }
 
-    // this is not tested, make sure to test this manually!
-    pub fn propagate(&mut self, rollouts: usize, rng: &mut ThreadRng) -> (usize, usize){
-        // returns (visits, player1 wins, player-1 wins)
+    /// Propagate which records the moves played below this node into `moves`, used for the all-moves-as-first statistics.
+    /// `draw_value` is the value of a draw for the player to move, it changes sign from one level to the next.
+    fn propagate_recording<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&mut self, rollouts: usize, config: &SearchConfig<S, R, E>, draw_value: f32, rng: &mut G, moves: &mut Vec<(T::Move, i8)>) -> (usize, usize, usize){
         self.visits += rollouts;
 
-        // if someone has already won, just return the winner
-        let score = self.game.get_score();
-        if score != 0 {
-            if score == 1 {
-                if self.player == -1 { self.wins += rollouts; }
-                else { self.losses += rollouts; }
-                return (rollouts, 0);
-            }
-            else if score == -1 {
-                if self.player == 1 { self.wins += rollouts; }
-                else { self.losses += rollouts; }
-                return (0, rollouts)
-            }
-            else {
-                panic!("Invalid score");
-            }
+        if self.proven == Proven::Unknown && ! self.created_children {
+            self.proven = terminal_value(&self.game, self.player);
         }
 
-        let (mut wins_1, mut wins_n1) = (0usize, 0usize);
+        // if the result is already known, just return it
+        let winner = match self.proven {
+            Proven::Win => -self.player,
+            Proven::Loss => self.player,
+            Proven::Draw => {
+                self.draws += rollouts;
+                return (0, 0, rollouts);
+            },
+            Proven::Unknown => 0
+        };
+        if winner != 0 {
+            if winner == -self.player { self.wins += rollouts; }
+            else { self.losses += rollouts; }
+
+            if winner == 1 { return (rollouts, 0, 0); }
+            else { return (0, rollouts, 0); }
+        }
+
+        let (mut wins_1, mut wins_n1, mut draws) = (0usize, 0usize, 0usize);
+        let amaf = config.selection.uses_amaf();
 
         // If the children have not been created yet, do rollouts and initialize children
         if ! self.created_children {
-            self.create_children();
+            let leaf_value = self.expand(config);
 
             for _ in 0..rollouts {
-                let res = self.rollout(rng);
+                let res = simulate(&mut self.game, self.player, config, rng, if amaf { Some(&mut *moves) } else { None }, leaf_value);
                 
                 if res == 1{
                     wins_1 += 1;
@@ -143,14 +459,25 @@ impl<T:GeneralGame> Node<T> {
                 else if res == -1 {
                     wins_n1 += 1;
                 }
+                else {
+                    draws += 1;
+                }
             }
         }
         // recursively call next children with highest score
         else {
-            let next = self.get_child_with_highest_score(rng);
+            let next = self.get_child_with_highest_score(&config.selection, draw_value, rng);
             if let Some(next_node_index) = next {
-                (wins_1, wins_n1) = self.children[next_node_index].propagate(rollouts, rng);
+                (wins_1, wins_n1, draws) = self.children[next_node_index].propagate_recording(rollouts, config, -draw_value, rng, moves);
+                if amaf {
+                    moves.push((self.children[next_node_index].move_index, self.player));
+                }
+            }
+            // every child is a proven loss but the node is not proven yet, the visit counts as a draw
+            else {
+                draws = rollouts;
             }
+            self.update_proven();
         }
 
         // update self
@@ -162,64 +489,166 @@ impl<T:GeneralGame> Node<T> {
             self.wins += wins_n1;
             self.losses += wins_1;
         }
+        self.draws += draws;
+
+        if amaf {
+            self.update_amaf(moves, wins_1, wins_n1, draws);
+        }
+
+        return (wins_1, wins_n1, draws);
+    }
+
+    /// updates the all-moves-as-first statistics of every child whose move was played by `self.player` later in the simulation
+    fn update_amaf(&mut self, moves: &[(T::Move, i8)], wins_1: usize, wins_n1: usize, draws: usize) {
+        let (wins, losses) = if self.player == 1 { (wins_1, wins_n1) } else { (wins_n1, wins_1) };
 
-        return (wins_1, wins_n1);
+        for child in self.children.iter_mut() {
+            if moves.contains(&(child.move_index, self.player)) {
+                child.amaf_visits += wins + losses + draws;
+                child.amaf_wins += wins;
+                child.amaf_losses += losses;
+                child.amaf_draws += draws;
+            }
+        }
     }
 
+    /// backs up the proven values of the children, which are from the point of view of `self.player`
+    fn update_proven(&mut self) {
+        let proven = backup_proven(self.children.iter().map(|child| child.proven));
+        if proven != Proven::Unknown {
+            self.proven = proven;
+        }
+    }
+
+    /// returns the child with most visits, except that a proven win is always returned and proven losses only if every move loses
     pub fn get_most_visited_child(&self) -> Option<&Node<T>> {
-        let mut most_visits = 0;
-        let mut most_visited : Option<&Node<T>> = None;
+        return most_visited_child(self.children.iter().map(|child| (child.stats(), child.proven))).map(|index| &self.children[index]);
+    }
 
-        for child in self.children.iter() {
-            if child.visits > most_visits {
-                most_visits = child.visits;
-                most_visited = Some(child);
+    /// Adds the statistics of `other`, a search of the same position, to this node and its children.
+    /// Deeper nodes of `other` are not merged.
+    pub fn merge(&mut self, other: &Node<T>) {
+        self.visits += other.visits;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.draws += other.draws;
+
+        if ! other.created_children {
+            return;
+        }
+        if ! self.created_children {
+            self.create_children();
+        }
+
+        for other_child in other.children.iter() {
+            let child = self.children.iter_mut().find(|child| child.move_index == other_child.move_index).unwrap();
+
+            child.visits += other_child.visits;
+            child.wins += other_child.wins;
+            child.losses += other_child.losses;
+            child.draws += other_child.draws;
+            child.amaf_visits += other_child.amaf_visits;
+            child.amaf_wins += other_child.amaf_wins;
+            child.amaf_losses += other_child.amaf_losses;
+            child.amaf_draws += other_child.amaf_draws;
+            if child.proven == Proven::Unknown {
+                child.proven = other_child.proven;
             }
         }
 
-        return most_visited;
+        if self.proven == Proven::Unknown {
+            self.proven = other.proven;
+        }
+        self.update_proven();
     }
 
-    pub fn predict(&mut self, iters: usize, rollouts: usize) {
-        let mut rng = rand::thread_rng();
-        for _ in 0..iters {
-            self.propagate(rollouts, &mut rng);
+    /// Moves this node down to the child reached by playing `move_index`, keeping the statistics of its subtree.
+    /// Returns false if the move is not available, in which case the node is left unchanged.
+    pub fn advance(&mut self, move_index: T::Move) -> bool {
+        if ! self.created_children {
+            self.create_children();
         }
+
+        let position = self.children.iter().position(|child| child.move_index == move_index);
+        if let Some(position) = position {
+            let child = self.children.swap_remove(position);
+            *self = child;
+            return true;
+        }
+
+        return false;
+    }
+
+    /// Runs `iters` iterations of the search, stopping early once the value of this node is proven or `config.early_stop` allows it.
+    /// Returns the number of iterations performed
+    pub fn predict<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&mut self, iters: usize, rollouts: usize, config: &SearchConfig<S, R, E>, rng: &mut G) -> usize {
+        for iter in 0..iters {
+            if self.proven != Proven::Unknown || config.early_stop.can_stop(self.children.iter().map(|child| (child.stats(), child.proven)), (iters - iter) * rollouts) {
+                return iter;
+            }
+            self.propagate(rollouts, config, rng);
+        }
+
+        return iters;
+    }
+
+    /// Runs the search until `budget_ms` milliseconds measured by `clock` have elapsed or the value of this node is proven,
+    /// returns the number of iterations performed. Of `config.early_stop` only the proven rule applies.
+    pub fn predict_for<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, C: Clock, G: Rng + ?Sized>(&mut self, budget_ms: f64, rollouts: usize, config: &SearchConfig<S, R, E>, clock: &mut C, rng: &mut G) -> usize {
+        let start = clock.now_ms();
+        let mut iters = 0usize;
+
+        while self.proven == Proven::Unknown && ! config.early_stop.can_stop(self.children.iter().map(|child| (child.stats(), child.proven)), usize::MAX) && clock.now_ms() - start < budget_ms {
+            self.propagate(rollouts, config, rng);
+            iters += 1;
+        }
+
+        return iters;
     }
 }
 
 
 #[cfg(test)]
-use super::tictactoe::TicTacToe;
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::connect4::Connect4;
+#[cfg(test)]
+use super::clock::FakeClock;
+#[cfg(test)]
+use super::selection::{Rave, Ucb1};
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
 #[test]
 fn test_node_new(){
     let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    let node = Node::new(tictactoe, -1, 0);
+    let node = Node::new(tictactoe, Square::default());
 
     let game = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    assert_eq!(node, Node {game: game, player: -1, visits: 0, wins: 0, losses: 0, children: Vec::<Node<TicTacToe>>::new(), created_children: false, move_index: 0})
+    assert_eq!(node, Node {game, player: -1, visits: 0, wins: 0, losses: 0, draws: 0, amaf_visits: 0, amaf_wins: 0, amaf_losses: 0, amaf_draws: 0, children: Vec::<Node<TicTacToe>>::new(), created_children: false, move_index: Square::default(), proven: Proven::Unknown, prior: 0.})
 }
 
 #[test]
 fn test_node_rollout(){
     let mut rng = rand::thread_rng();
 
-    let tictactoe = TicTacToe::from_string("XX.\nOOX\nOXO").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
-
-    assert_eq!(node.rollout(&mut rng), -1);
+    let tictactoe = TicTacToe::from_string("OX.\nXOX\nOX.").unwrap();
+    let node = Node::new(tictactoe, Square::default());
+    assert_eq!(node.rollout(&UniformRollout, &mut rng), -1);
 
-    node.player = 1;
-    assert_eq!(node.rollout(&mut rng), 1);
+    let tictactoe = TicTacToe::from_string("XX.\nOOX\nOXO").unwrap();
+    let node = Node::new(tictactoe, Square::default());
+    assert_eq!(node.rollout(&UniformRollout, &mut rng), 1);
 
     let tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
-    let node = Node::new(tictactoe, -1, 0);
+    let node = Node::new(tictactoe, Square::default());
     const MAX_ITER:usize = 10000;
     let mut iter = 0usize;
     let (mut player_1, mut player_2, mut draw) = (false, false, false);
     while !(player_1&&player_2&&draw){
         iter += 1;
-        let res = node.rollout(&mut rng);
+        let res = node.rollout(&UniformRollout, &mut rng);
         match res {
             -1 => player_1=true,
             0 => draw = true,
@@ -233,51 +662,33 @@ fn test_node_rollout(){
 #[test]
 fn test_node_create_children(){
     let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
+    let mut node = Node::new(tictactoe, Square::default());
 
     node.create_children();
     assert_eq!(node.children.len(), 4);
     assert_eq!(node.children[0].game, TicTacToe::from_string("O.X\nO..\nXXO").unwrap());
-}
-
-#[test]
-fn test_node_score(){
-    let tictactoe = TicTacToe::from_string("..X\nO..\nXXO").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
-
-    assert_eq!(node.get_score(1), f32::INFINITY);
-    node.visits = 1;
-    node.wins = 1;
-    assert_eq!(node.get_score(1), 1.);
-
-    node.visits = 2;
-    node.wins = 1;
-    assert!((node.get_score(2) - 2.1651).abs() < 0.0001);
-
-    node.visits = 5;
-    node.wins = 1;
-    node.losses = 2;
-    assert!((node.get_score(10) - 1.7194).abs() < 0.0001);
+    assert!(node.children.iter().all(|child| child.prior == 0.25));
 }
 
 #[test]
 fn test_node_next_maxscore(){
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(0);
+    let policy = Ucb1::default();
 
     let tictactoe = TicTacToe::from_string("X.O\nOXO\nXX.").unwrap();
-    let mut node = Node::new(tictactoe, -1, 0);
+    let mut node = Node::new(tictactoe, Square::default());
 
-    assert_eq!(node.get_child_with_highest_score(&mut rng), None);
+    assert_eq!(node.get_child_with_highest_score(&policy, 0., &mut rng), None);
 
     node.create_children();
 
-    assert_ne!(node.get_child_with_highest_score(&mut rng), None);
+    assert_ne!(node.get_child_with_highest_score(&policy, 0., &mut rng), None);
 
     const MAX_ITER :usize = 10;
     let mut iter = 0usize;
     loop {
         iter += 1;
-        if node.get_child_with_highest_score(&mut rng).unwrap() != 0{
+        if node.get_child_with_highest_score(&policy, 0., &mut rng).unwrap() != 0{
             break;
         }
         assert!(iter<MAX_ITER);
@@ -290,10 +701,10 @@ fn test_node_next_maxscore(){
     node.children[1].visits = 2;
     node.visits=3;
 
-    assert!((node.children[0].get_score(3) - 1.9646).abs() < 0.0001);
-    assert!((node.children[1].get_score(3) - 2.5963).abs() < 0.0001);
+    assert!((policy.score(&node.children[0].stats(), 3, 0., &mut rng) - 1.9646).abs() < 0.0001);
+    assert!((policy.score(&node.children[1].stats(), 3, 0., &mut rng) - 2.5963).abs() < 0.0001);
 
-    assert_eq!(node.get_child_with_highest_score(&mut rng), Some(1));
+    assert_eq!(node.get_child_with_highest_score(&policy, 0., &mut rng), Some(1));
 
     
     node.children[1].wins = 10;
@@ -301,5 +712,275 @@ fn test_node_next_maxscore(){
     node.children[1].visits = 20;
     node.visits = 21;
 
-    assert_eq!(node.get_child_with_highest_score(&mut rng), Some(0));
-}
\ No newline at end of file
+    assert_eq!(node.get_child_with_highest_score(&policy, 0., &mut rng), Some(0));
+}
+
+#[test]
+fn test_node_predict_for(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+
+    let mut clock = FakeClock { time: 0., step: 1. };
+    let iters = node.predict_for(10., 1, &SearchConfig::<Ucb1, UniformRollout>::default(), &mut clock, &mut rng);
+    assert_eq!(iters, 9);
+    assert_eq!(node.visits, 9);
+
+    let mut clock = FakeClock { time: 0., step: 1. };
+    assert_eq!(node.predict_for(0., 1, &SearchConfig::<Ucb1, UniformRollout>::default(), &mut clock, &mut rng), 0);
+    assert_eq!(node.visits, 9);
+}
+
+#[test]
+fn test_node_advance(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+
+    node.predict(100, 1, &SearchConfig::<Ucb1, UniformRollout>::default(), &mut rng);
+    let child = node.children.iter().find(|child| child.move_index == Square(1,1)).unwrap().clone();
+
+    assert!(node.advance(Square(1,1)));
+    assert_eq!(node, child);
+    assert_eq!(node.player, -1);
+    assert_eq!(node.game, TicTacToe::from_string("...\n.X.\n...").unwrap());
+
+    assert!(! node.advance(Square(1,1)));
+    assert_eq!(node, child);
+
+    // children which were not expanded yet are created on demand
+    let tictactoe = TicTacToe::from_string("...\n...\n...").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+    assert!(node.advance(Square(0,0)));
+    assert!(node.advance(Square(2,2)));
+    assert_eq!(node.player, 1);
+    assert_eq!(node.visits, 0);
+    assert_eq!(node.game, TicTacToe::from_string("X..\n...\n..O").unwrap());
+}
+
+#[test]
+fn test_node_proven(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    // X wins by playing the top right corner
+    let tictactoe = TicTacToe::from_string("XX.\nOO.\n...").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+
+    let iters = node.predict(1000, 1, &config, &mut rng);
+    assert!(iters < 1000);
+    assert_eq!(node.proven, Proven::Loss);
+    assert_eq!(node.get_most_visited_child().unwrap().move_index, Square(0,2));
+    assert_eq!(node.get_most_visited_child().unwrap().proven, Proven::Win);
+
+    // proven nodes are not searched any further
+    let visits = node.visits;
+    assert_eq!(node.predict(1000, 1, &config, &mut rng), 0);
+    assert_eq!(node.visits, visits);
+
+    // every move of O loses
+    let tictactoe = TicTacToe::from_string("X.X\nOX.\nO..").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+    node.predict(10000, 1, &config, &mut rng);
+    assert_eq!(node.proven, Proven::Win);
+
+    // the only remaining move draws
+    let tictactoe = TicTacToe::from_string("XOX\nXOO\nOX.").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+    assert_eq!(node.predict(100, 1, &config, &mut rng), 2);
+    assert_eq!(node.proven, Proven::Draw);
+    assert_eq!(node.children[0].proven, Proven::Draw);
+}
+
+#[test]
+fn test_node_proven_loss_not_selected(){
+    let mut rng = rand::thread_rng();
+    let policy = Ucb1::default();
+
+    let tictactoe = TicTacToe::from_string("X.O\nOXO\nXX.").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+    node.create_children();
+
+    node.children[0].proven = Proven::Loss;
+    for _ in 0..100 {
+        assert_eq!(node.get_child_with_highest_score(&policy, 0., &mut rng), Some(1));
+    }
+
+    node.children[1].proven = Proven::Loss;
+    assert_eq!(node.get_child_with_highest_score(&policy, 0., &mut rng), None);
+
+    node.children[0].proven = Proven::Win;
+    assert_eq!(node.get_child_with_highest_score(&policy, 0., &mut rng), Some(0));
+}
+
+#[test]
+fn test_node_draws(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    // the game ends in a draw
+    let tictactoe = TicTacToe::from_string("XOX\nXOO\nOX.").unwrap();
+    let mut node = Node::new(tictactoe, Square::default());
+    node.predict(100, 1, &config, &mut rng);
+    assert!(node.visits > 0);
+    assert_eq!(node.draws, node.visits);
+    assert_eq!(node.wins, 0);
+    assert_eq!(node.losses, 0);
+
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    node.predict(200, 1, &config, &mut rng);
+    assert_eq!(node.visits, node.wins + node.losses + node.draws);
+    for child in node.children.iter() {
+        assert_eq!(child.visits, child.wins + child.losses + child.draws);
+    }
+}
+
+#[test]
+fn test_node_value(){
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    assert_eq!(node.value(0.), 0.);
+
+    node.visits = 10;
+    node.wins = 4;
+    node.losses = 2;
+    node.draws = 4;
+    assert!((node.value(0.) - 0.2).abs() < 0.0001);
+    assert!((node.value(0.5) - 0.4).abs() < 0.0001);
+    assert!((node.value(-0.5) - 0.).abs() < 0.0001);
+}
+
+#[test]
+fn test_clamp_draw_value(){
+    assert_eq!(clamp_draw_value(0.5), 0.5);
+    assert_eq!(clamp_draw_value(3.), 1.);
+    assert_eq!(clamp_draw_value(f32::NEG_INFINITY), -1.);
+    assert_eq!(clamp_draw_value(f32::NAN), 0.);
+
+    let config = SearchConfig::<Ucb1, UniformRollout> { draw_value: -2., ..Default::default() };
+    assert_eq!(config.clamped_draw_value(), -1.);
+}
+
+#[test]
+fn test_node_amaf(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Rave, UniformRollout>::default();
+
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    node.predict(200, 1, &config, &mut rng);
+
+    for child in node.children.iter() {
+        // every simulation through the child also counts for its move
+        assert!(child.amaf_visits >= child.visits);
+        assert_eq!(child.amaf_visits, child.amaf_wins + child.amaf_losses + child.amaf_draws);
+    }
+    // the moves of a simulation are shared between several children
+    let amaf_visits: usize = node.children.iter().map(|child| child.amaf_visits).sum();
+    assert!(amaf_visits > node.visits);
+
+    // without RAVE no statistics are collected
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    node.predict(200, 1, &config, &mut rng);
+    assert!(node.children.iter().all(|child| child.amaf_visits == 0));
+}
+
+#[test]
+fn test_node_amaf_value(){
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    assert_eq!(node.amaf_value(0.), 0.);
+
+    node.amaf_visits = 10;
+    node.amaf_wins = 6;
+    node.amaf_losses = 2;
+    node.amaf_draws = 2;
+    assert!((node.amaf_value(0.) - 0.4).abs() < 0.0001);
+    assert!((node.amaf_value(1.) - 0.6).abs() < 0.0001);
+}
+
+#[test]
+fn test_node_merge(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    let mut first = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    let mut second = first.clone();
+    first.predict(100, 1, &config, &mut rng);
+    second.predict(50, 1, &config, &mut rng);
+
+    let mut merged = first.clone();
+    merged.merge(&second);
+    assert_eq!(merged.visits, 150);
+    assert_eq!(merged.wins, first.wins + second.wins);
+    assert_eq!(merged.draws, first.draws + second.draws);
+    for child in merged.children.iter() {
+        let first_child = first.children.iter().find(|c| c.move_index == child.move_index).unwrap();
+        let second_child = second.children.iter().find(|c| c.move_index == child.move_index).unwrap();
+        assert_eq!(child.visits, first_child.visits + second_child.visits);
+        assert_eq!(child.losses, first_child.losses + second_child.losses);
+    }
+
+    // merging into a node which was not expanded yet
+    let mut empty = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    empty.merge(&second);
+    assert_eq!(empty.visits, 50);
+    assert_eq!(empty.children.len(), 9);
+    assert_eq!(empty.children.iter().map(|c| c.visits).sum::<usize>(), 49);
+
+    // proven values are merged as well
+    let mut solved = Node::new(TicTacToe::from_string("XX.\nOO.\n...").unwrap(), Square::default());
+    solved.predict(1000, 1, &config, &mut rng);
+    let mut unsolved = Node::new(TicTacToe::from_string("XX.\nOO.\n...").unwrap(), Square::default());
+    unsolved.merge(&solved);
+    assert_eq!(unsolved.proven, Proven::Loss);
+}
+
+#[test]
+fn test_early_stop(){
+    let stats = |visits| Stats { visits, ..Stats::default() };
+    let children = [(stats(60), Proven::Unknown), (stats(30), Proven::Unknown), (stats(10), Proven::Loss)];
+
+    assert!(! EarlyStop::default().can_stop(children.iter().cloned(), 0));
+
+    let visit_gap = EarlyStop { visit_gap: true, proven: false };
+    assert!(visit_gap.can_stop(children.iter().cloned(), 29));
+    assert!(! visit_gap.can_stop(children.iter().cloned(), 30));
+    assert!(! visit_gap.can_stop([].into_iter(), 0));
+
+    let proven = EarlyStop { visit_gap: false, proven: true };
+    assert!(! proven.can_stop(children.iter().cloned(), 0));
+    let children = [(stats(10), Proven::Unknown), (stats(30), Proven::Loss), (stats(60), Proven::Loss)];
+    assert!(proven.can_stop(children.iter().cloned(), 1000));
+    assert!(! visit_gap.can_stop(children.iter().cloned(), 1000));
+}
+
+#[test]
+fn test_node_predict_early_stop(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    // O has to block the last column, every other move loses
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ......X\n\
+                        O.....X\n\
+                        O.....X\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+    let mut node = Node::new(game.clone(), 0);
+    assert_eq!(node.predict(2000, 1, &config, &mut rng), 2000);
+    assert_eq!(node.get_most_visited_child().unwrap().move_index, 6);
+
+    config.early_stop = EarlyStop { visit_gap: true, proven: false };
+    let mut stopped = Node::new(game.clone(), 0);
+    let iters = stopped.predict(2000, 1, &config, &mut rng);
+    assert!(iters < 2000);
+    assert_eq!(stopped.get_most_visited_child().unwrap().move_index, 6);
+
+    config.early_stop = EarlyStop { visit_gap: false, proven: true };
+    let mut stopped = Node::new(game, 0);
+    assert!(stopped.predict(2000, 1, &config, &mut rng) < iters);
+    assert_eq!(stopped.get_most_visited_child().unwrap().move_index, 6);
+    assert_eq!(stopped.proven, Proven::Unknown);
+}
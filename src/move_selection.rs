@@ -0,0 +1,193 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use super::mcts::{Proven, Stats};
+
+/// Decides which move is played after the search. A proven win is always played and proven losses only if every move loses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveSelection {
+    /// the most visited move, ties are broken by the value
+    MaxVisits,
+    /// the move with the highest value, ties are broken by the visits
+    MaxValue,
+    /// the most visited move if it also has the highest value, otherwise the search is extended by up to `max_extension`
+    /// iterations until they agree, see `Tree::choose_move`
+    RobustMax { max_extension: usize },
+    /// the move with the highest lower confidence bound `value - constant / sqrt(visits)`
+    SecureChild { constant: f32 },
+    /// a random move with probability proportional to `visits^(1 / temperature)`, the most visited move if the temperature
+    /// is not positive and finite
+    Sample { temperature: f32 }
+}
+
+impl Default for MoveSelection {
+    fn default() -> MoveSelection {
+        return MoveSelection::MaxVisits;
+    }
+}
+
+/// index of the largest key, the first one if several are equal
+fn argmax<K: PartialOrd, I: Iterator<Item = (usize, K)>>(keys: I) -> Option<usize> {
+    let mut best: Option<(usize, K)> = None;
+
+    for (index, key) in keys {
+        if best.as_ref().is_none_or(|(_, best_key)| key > *best_key) {
+            best = Some((index, key));
+        }
+    }
+
+    return best.map(|(index, _)| index);
+}
+
+impl MoveSelection {
+    /// takes one of "max-visits", "max-value", "robust-max", "secure-child", "sample", `parameter` is the maximum extension
+    /// of "robust-max", the constant of "secure-child" and the temperature of "sample", which has to be positive and finite
+    pub fn from_name(name: &str, parameter: f32) -> Option<MoveSelection> {
+        return match name {
+            "max-visits" => Some(MoveSelection::MaxVisits),
+            "max-value" => Some(MoveSelection::MaxValue),
+            "robust-max" => Some(MoveSelection::RobustMax { max_extension: parameter as usize }),
+            "secure-child" => Some(MoveSelection::SecureChild { constant: parameter }),
+            "sample" if parameter.is_finite() && parameter > 0. => Some(MoveSelection::Sample { temperature: parameter }),
+            _ => None
+        };
+    }
+
+    /// Picks the move given the statistics and proven values of the children of the root. Returns the index of the child,
+    /// `None` if no child was visited. `RobustMax` returns the most visited child, whether or not it has the highest value.
+    pub fn choose<I: Iterator<Item = (Stats, Proven)> + Clone, G: Rng + ?Sized>(&self, children: I, draw_value: f32, rng: &mut G) -> Option<usize> {
+        let candidates = candidates(children);
+
+        return match self {
+            MoveSelection::MaxVisits | MoveSelection::RobustMax { .. } => max_visits(&candidates, draw_value),
+            MoveSelection::MaxValue => max_value(&candidates, draw_value),
+            MoveSelection::SecureChild { constant } =>
+                argmax(candidates.iter().map(|(index, stats)| (*index, stats.value(draw_value) - constant / (stats.visits as f32).sqrt()))),
+            MoveSelection::Sample { temperature } if ! temperature.is_finite() || *temperature <= 0. => max_visits(&candidates, draw_value),
+            MoveSelection::Sample { temperature } => {
+                let max_visits = candidates.iter().map(|(_, stats)| stats.visits).max()? as f64;
+
+                // scaled by the maximum to avoid overflow for low temperatures
+                let weight = |visits: usize| (visits as f64 / max_visits).powf(1. / *temperature as f64);
+                candidates.choose_weighted(rng, |(_, stats)| weight(stats.visits)).ok().map(|(index, _)| *index)
+            }
+        };
+    }
+
+    /// whether the most visited child also has the highest value, always true for the other selections
+    pub(crate) fn agrees<I: Iterator<Item = (Stats, Proven)> + Clone>(&self, children: I, draw_value: f32) -> bool {
+        if let MoveSelection::RobustMax { .. } = self {
+            let candidates = candidates(children);
+            return max_visits(&candidates, draw_value) == max_value(&candidates, draw_value);
+        }
+
+        return true;
+    }
+}
+
+/// indices and statistics of the visited children which may be played, only the proven win if there is one
+fn candidates<I: Iterator<Item = (Stats, Proven)> + Clone>(children: I) -> Vec<(usize, Stats)> {
+    if let Some(index) = children.clone().position(|(_, proven)| proven == Proven::Win) {
+        return vec![(index, children.clone().nth(index).unwrap().0)];
+    }
+    let all_lose = children.clone().all(|(_, proven)| proven == Proven::Loss);
+
+    return children.enumerate()
+        .filter(|(_, (stats, proven))| stats.visits > 0 && (*proven != Proven::Loss || all_lose))
+        .map(|(index, (stats, _))| (index, stats))
+        .collect();
+}
+
+fn max_visits(candidates: &[(usize, Stats)], draw_value: f32) -> Option<usize> {
+    return argmax(candidates.iter().map(|(index, stats)| (*index, (stats.visits, stats.value(draw_value)))));
+}
+
+fn max_value(candidates: &[(usize, Stats)], draw_value: f32) -> Option<usize> {
+    return argmax(candidates.iter().map(|(index, stats)| (*index, (stats.value(draw_value), stats.visits))));
+}
+
+
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+#[cfg(test)]
+fn child(visits: usize, wins: usize, losses: usize, proven: Proven) -> (Stats, Proven) {
+    return (Stats { visits, wins, losses, draws: visits - wins - losses, ..Stats::default() }, proven);
+}
+
+#[test]
+fn test_move_selection_choose(){
+    let mut rng = StdRng::seed_from_u64(0);
+    // values 0.2, 0.6, 0.5 and -0.5
+    let children = [child(50, 20, 10, Proven::Unknown), child(10, 7, 1, Proven::Unknown), child(40, 25, 5, Proven::Unknown), child(2, 0, 1, Proven::Unknown)];
+    let choose = |selection: MoveSelection, rng: &mut StdRng| selection.choose(children.iter().cloned(), 0., rng);
+
+    assert_eq!(choose(MoveSelection::MaxVisits, &mut rng), Some(0));
+    assert_eq!(choose(MoveSelection::MaxValue, &mut rng), Some(1));
+    assert_eq!(choose(MoveSelection::RobustMax { max_extension: 100 }, &mut rng), Some(0));
+    // 0.6 - 1 / sqrt(10) < 0.5 - 1 / sqrt(40)
+    assert_eq!(choose(MoveSelection::SecureChild { constant: 1. }, &mut rng), Some(2));
+    assert_eq!(choose(MoveSelection::SecureChild { constant: 0. }, &mut rng), Some(1));
+    assert_eq!(choose(MoveSelection::Sample { temperature: 0.01 }, &mut rng), Some(0));
+
+    // ties of the visits are broken by the value
+    let tied = [child(10, 2, 2, Proven::Unknown), child(10, 5, 2, Proven::Unknown)];
+    assert_eq!(MoveSelection::MaxVisits.choose(tied.iter().cloned(), 0., &mut rng), Some(1));
+
+    // nothing was visited yet
+    let unvisited = [child(0, 0, 0, Proven::Unknown), child(0, 0, 0, Proven::Unknown)];
+    for selection in [MoveSelection::MaxVisits, MoveSelection::MaxValue, MoveSelection::Sample { temperature: 1. }] {
+        assert_eq!(selection.choose(unvisited.iter().cloned(), 0., &mut rng), None);
+    }
+}
+
+#[test]
+fn test_move_selection_proven(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let children = [child(50, 50, 0, Proven::Loss), child(10, 0, 10, Proven::Unknown), child(1, 1, 0, Proven::Win)];
+    let losing = [child(50, 0, 50, Proven::Loss), child(10, 0, 10, Proven::Loss)];
+
+    for selection in [MoveSelection::MaxVisits, MoveSelection::MaxValue, MoveSelection::SecureChild { constant: 1. }, MoveSelection::Sample { temperature: 1. }] {
+        assert_eq!(selection.choose(children.iter().cloned(), 0., &mut rng), Some(2));
+        assert_eq!(selection.choose(children[..2].iter().cloned(), 0., &mut rng), Some(1));
+        assert!(selection.choose(losing.iter().cloned(), 0., &mut rng).is_some());
+    }
+}
+
+#[test]
+fn test_move_selection_sample(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let children = [child(30, 10, 10, Proven::Unknown), child(10, 5, 5, Proven::Unknown), child(0, 0, 0, Proven::Unknown)];
+
+    let mut counts = [0usize; 3];
+    for _ in 0..4000 {
+        counts[MoveSelection::Sample { temperature: 1. }.choose(children.iter().cloned(), 0., &mut rng).unwrap()] += 1;
+    }
+    // proportional to the visits, unvisited moves are never sampled
+    assert!(counts[0] > 2 * counts[1] && counts[0] < 4 * counts[1]);
+    assert_eq!(counts[2], 0);
+
+    let mut counts = [0usize; 3];
+    for _ in 0..4000 {
+        counts[MoveSelection::Sample { temperature: 100. }.choose(children.iter().cloned(), 0., &mut rng).unwrap()] += 1;
+    }
+    assert!(counts[1] > counts[0] * 3 / 4);
+
+    // invalid temperatures play the most visited move
+    for temperature in [0., -1., f32::NAN, f32::INFINITY] {
+        assert_eq!(MoveSelection::Sample { temperature }.choose(children.iter().cloned(), 0., &mut rng), Some(0));
+    }
+}
+
+#[test]
+fn test_move_selection_from_name(){
+    assert_eq!(MoveSelection::from_name("max-visits", 0.), Some(MoveSelection::MaxVisits));
+    assert_eq!(MoveSelection::from_name("max-value", 0.), Some(MoveSelection::MaxValue));
+    assert_eq!(MoveSelection::from_name("robust-max", 500.), Some(MoveSelection::RobustMax { max_extension: 500 }));
+    assert_eq!(MoveSelection::from_name("secure-child", 2.), Some(MoveSelection::SecureChild { constant: 2. }));
+    assert_eq!(MoveSelection::from_name("sample", 0.5), Some(MoveSelection::Sample { temperature: 0.5 }));
+    for temperature in [0., -1., f32::NAN, f32::INFINITY] {
+        assert_eq!(MoveSelection::from_name("sample", temperature), None);
+    }
+    assert_eq!(MoveSelection::from_name("random", 0.), None);
+}
@@ -0,0 +1,387 @@
+use std::path::Path;
+
+use super::evaluation::Evaluator;
+use super::mcts::GeneralGame;
+
+/// Input of a network, a game which can be turned into a vector of numbers
+pub trait Encode: GeneralGame {
+    /// the position seen by `player`, who is to move
+    fn encode(&self, player: i8) -> Vec<f32>;
+    /// output of the policy head which belongs to `index`
+    fn policy_index(&self, index: Self::Move) -> usize;
+    /// number of outputs of the policy head, larger than every `policy_index`
+    fn policy_size(&self) -> usize;
+}
+
+/// Fully connected layer, `outputs = weights * inputs + biases`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    /// row-major, one row of `inputs()` weights for every output
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>
+}
+
+impl Layer {
+    pub fn inputs(&self) -> usize {
+        return self.weights.len() / self.biases.len();
+    }
+
+    pub fn outputs(&self) -> usize {
+        return self.biases.len();
+    }
+
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        return Vec::from_iter(self.weights.chunks_exact(input.len()).zip(self.biases.iter()).map(|(row, bias)| {
+            bias + row.iter().zip(input.iter()).map(|(weight, x)| weight * x).sum::<f32>()
+        }));
+    }
+}
+
+/// Multilayer perceptron with a policy and a value head, evaluated on the CPU. The hidden layers use ReLU, the policy gives
+/// one logit per move index and the value is squashed by tanh into the value for the player to move.
+/// Built by `Mlp::new` or read from a weights file, so that the layers always fit together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mlp {
+    hidden: Vec<Layer>,
+    policy: Layer,
+    value: Layer
+}
+
+/// identifies a file of weights, followed by the version of the format
+const MAGIC: &[u8; 4] = b"MLPW";
+const VERSION: u32 = 1;
+
+/// reads little-endian numbers from the bytes of a weights file
+struct Reader<'a> {
+    bytes: &'a [u8]
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        if self.bytes.len() < count {
+            return Err("the weights file is truncated".to_string());
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        return Ok(taken);
+    }
+
+    fn u32(&mut self) -> Result<usize, String> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize);
+    }
+
+    /// counts come from the file, a count too large for memory is reported like any other missing data
+    fn f32s(&mut self, count: usize) -> Result<Vec<f32>, String> {
+        let bytes = count.checked_mul(4).ok_or_else(|| "the weights file is truncated".to_string())?;
+        return Ok(Vec::from_iter(self.take(bytes)?.chunks_exact(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))));
+    }
+
+    fn layer(&mut self, inputs: usize, outputs: usize) -> Result<Layer, String> {
+        let count = inputs.checked_mul(outputs).ok_or_else(|| "the weights file is truncated".to_string())?;
+        let weights = self.f32s(count)?;
+        let biases = self.f32s(outputs)?;
+        return Ok(Layer { weights, biases });
+    }
+}
+
+impl Mlp {
+    /// checks that the layers fit together and that the value head has a single output
+    pub fn new(hidden: Vec<Layer>, policy: Layer, value: Layer) -> Result<Mlp, String> {
+        if hidden.iter().chain([&policy, &value]).any(|layer| layer.biases.is_empty() || layer.weights.is_empty() || layer.weights.len() % layer.biases.len() != 0) {
+            return Err("every layer needs at least one input and output".to_string());
+        }
+        for (layer, next) in hidden.iter().zip(hidden.iter().skip(1)) {
+            if layer.outputs() != next.inputs() {
+                return Err(format!("a layer with {} outputs is followed by a layer with {} inputs", layer.outputs(), next.inputs()));
+            }
+        }
+        let features = hidden.last().map_or(policy.inputs(), |layer| layer.outputs());
+        if policy.inputs() != features || value.inputs() != features {
+            return Err(format!("the heads take {} and {} inputs instead of {}", policy.inputs(), value.inputs(), features));
+        }
+        if value.outputs() != 1 {
+            return Err(format!("the value head has {} outputs instead of 1", value.outputs()));
+        }
+
+        return Ok(Mlp { hidden, policy, value });
+    }
+
+    /// number of inputs of the network
+    pub fn inputs(&self) -> usize {
+        return self.hidden.first().unwrap_or(&self.policy).inputs();
+    }
+
+    /// number of outputs of the policy head
+    pub fn moves(&self) -> usize {
+        return self.policy.outputs();
+    }
+
+    /// checks that the network takes the encoding of `game` and has an output for every move of it
+    pub fn check<T: Encode>(&self, game: &T) -> Result<(), String> {
+        let inputs = game.encode(game.player()).len();
+        if inputs != self.inputs() {
+            return Err(format!("the network takes {} inputs, but the game is encoded by {}", self.inputs(), inputs));
+        }
+        if self.moves() < game.policy_size() {
+            return Err(format!("the network has {} policy outputs, but the game needs {}", self.moves(), game.policy_size()));
+        }
+
+        return Ok(());
+    }
+
+    /// Reads the weights written by `to_bytes`: "MLPW", the version, the number of inputs, the number of hidden layers and
+    /// their sizes, the number of policy outputs, then the weights and biases of every layer in order, ending with the policy
+    /// and the value head. Everything is little-endian, sizes are u32 and weights f32.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Mlp, String> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != MAGIC {
+            return Err("not a weights file".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION as usize {
+            return Err(format!("unsupported weights version {}", version));
+        }
+
+        let inputs = reader.u32()?;
+        let hidden_count = reader.u32()?;
+        let mut sizes = vec![inputs];
+        for _ in 0..hidden_count {
+            sizes.push(reader.u32()?);
+        }
+        let moves = reader.u32()?;
+
+        let mut hidden = Vec::new();
+        for pair in sizes.windows(2) {
+            hidden.push(reader.layer(pair[0], pair[1])?);
+        }
+        let features = *sizes.last().unwrap();
+        let policy = reader.layer(features, moves)?;
+        let value = reader.layer(features, 1)?;
+
+        if ! reader.bytes.is_empty() {
+            return Err("unexpected data after the weights".to_string());
+        }
+
+        return Mlp::new(hidden, policy, value);
+    }
+
+    /// the format read by `from_bytes`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let sizes = [VERSION, self.inputs() as u32, self.hidden.len() as u32].into_iter()
+            .chain(self.hidden.iter().map(|layer| layer.outputs() as u32))
+            .chain([self.moves() as u32]);
+        for size in sizes {
+            bytes.extend(size.to_le_bytes());
+        }
+
+        for layer in self.hidden.iter().chain([&self.policy, &self.value]) {
+            for weight in layer.weights.iter().chain(layer.biases.iter()) {
+                bytes.extend(weight.to_le_bytes());
+            }
+        }
+
+        return bytes;
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Mlp, String> {
+        let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+        return Mlp::from_bytes(&bytes);
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        return std::fs::write(path, self.to_bytes()).map_err(|error| error.to_string());
+    }
+
+    /// policy logits and the value of the encoded position, panics if `input` does not have `inputs()` values
+    pub fn forward(&self, input: &[f32]) -> (Vec<f32>, f32) {
+        assert_eq!(input.len(), self.inputs(), "the network takes {} inputs", self.inputs());
+
+        let mut features = input.to_vec();
+        for layer in self.hidden.iter() {
+            features = layer.forward(&features);
+            for x in features.iter_mut() {
+                *x = x.max(0.);
+            }
+        }
+
+        return (self.policy.forward(&features), self.value.forward(&features)[0].tanh());
+    }
+}
+
+impl<T: Encode> Evaluator<T> for Mlp {
+    fn evaluate(&self, game: &T, player: i8) -> f32 {
+        return self.evaluate_with_priors(game, player).1;
+    }
+
+    /// Softmax of the logits of the available moves, finished games are valued exactly without running the network.
+    /// A network which does not fit the game (see `check`) gives uniform priors and an even value instead of panicking.
+    fn evaluate_with_priors(&self, game: &T, player: i8) -> (Option<Vec<f32>>, f32) {
+        let outcome = game.get_outcome();
+        if outcome.is_over() {
+            return (None, (outcome.winner() * player) as f32);
+        }
+
+        let input = game.encode(player);
+        if input.len() != self.inputs() || self.moves() < game.policy_size() {
+            return (None, 0.);
+        }
+
+        let available = game.get_available();
+        let (logits, value) = self.forward(&input);
+        let logits = Vec::from_iter(available.iter().map(|&index| logits[game.policy_index(index)]));
+        let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let exps = Vec::from_iter(logits.iter().map(|logit| (logit - max).exp()));
+        let sum: f32 = exps.iter().sum();
+
+        return (Some(Vec::from_iter(exps.iter().map(|exp| exp / sum))), value);
+    }
+}
+
+
+#[cfg(test)]
+use super::connect4::Connect4;
+#[cfg(test)]
+use super::mcts::{Node, SearchConfig};
+#[cfg(test)]
+use super::evaluation::LeafEvaluation;
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::Puct;
+#[cfg(test)]
+use super::tree::Tree;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+/// network for 6x7 Connect4 with one hidden unit, one more than the tokens of the player to move, which is multiplied by `logit`
+/// for `column` and by zero for the other columns
+#[cfg(test)]
+fn column_network(column: usize, logit: f32) -> Mlp {
+    let mut weights = vec![0.; 84];
+    weights[..42].fill(1.);
+    let mut policy = vec![0.; 7];
+    policy[column] = logit;
+
+    return Mlp::new(vec![Layer { weights, biases: vec![1.] }], Layer { weights: policy, biases: vec![0.; 7] }, Layer { weights: vec![0.5], biases: vec![-1.5] }).unwrap();
+}
+
+#[test]
+fn test_mlp_forward(){
+    let network = Mlp::new(
+        vec![Layer { weights: vec![1., -1., 2., 1.], biases: vec![0., -1.] }],
+        Layer { weights: vec![1., 0., 0., 1., 1., 1.], biases: vec![0., 0., 1.] },
+        Layer { weights: vec![0.5, -0.5], biases: vec![0.] }
+    ).unwrap();
+    assert_eq!(network.inputs(), 2);
+
+    // hidden layer gives relu(1 - 2) = 0 and relu(2 + 2 - 1) = 3
+    let (logits, value) = network.forward(&[1., 2.]);
+    assert_eq!(logits, vec![0., 3., 4.]);
+    assert!((value - (-1.5f32).tanh()).abs() < 0.0001);
+
+    // without hidden layers the heads read the input directly
+    let linear = Mlp::new(vec![], Layer { weights: vec![1., 2.], biases: vec![0.] }, Layer { weights: vec![0., 0.], biases: vec![0.] }).unwrap();
+    assert_eq!(linear.forward(&[3., 4.]), (vec![11.], 0.));
+}
+
+#[test]
+fn test_mlp_shapes(){
+    let layer = |inputs: usize, outputs: usize| Layer { weights: vec![0.; inputs * outputs], biases: vec![0.; outputs] };
+
+    assert!(Mlp::new(vec![layer(4, 3), layer(3, 2)], layer(2, 7), layer(2, 1)).is_ok());
+    assert!(Mlp::new(vec![layer(4, 3), layer(2, 2)], layer(2, 7), layer(2, 1)).is_err());
+    assert!(Mlp::new(vec![layer(4, 3)], layer(2, 7), layer(3, 1)).is_err());
+    assert!(Mlp::new(vec![layer(4, 3)], layer(3, 7), layer(3, 2)).is_err());
+    assert!(Mlp::new(vec![], layer(0, 7), layer(0, 1)).is_err());
+}
+
+#[test]
+fn test_mlp_bytes(){
+    let network = column_network(3, 2.);
+    let bytes = network.to_bytes();
+    assert_eq!(&bytes[..8], b"MLPW\x01\x00\x00\x00");
+    assert_eq!(Mlp::from_bytes(&bytes), Ok(network.clone()));
+
+    assert!(Mlp::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Mlp::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+    assert!(Mlp::from_bytes(b"MLPW\x02\x00\x00\x00").is_err());
+    assert!(Mlp::from_bytes(b"weights").is_err());
+    // sizes whose product overflows
+    let huge = [b"MLPW".as_slice(), &1u32.to_le_bytes(), &u32::MAX.to_le_bytes(), &1u32.to_le_bytes(), &u32::MAX.to_le_bytes(), &7u32.to_le_bytes()].concat();
+    assert_eq!(Mlp::from_bytes(&huge), Err("the weights file is truncated".to_string()));
+
+    let path = std::env::temp_dir().join(format!("connect4_mcts_test_{}.mlp", std::process::id()));
+    network.save(&path).unwrap();
+    assert_eq!(Mlp::load(&path), Ok(network));
+    std::fs::remove_file(&path).unwrap();
+    assert!(Mlp::load(&path).is_err());
+}
+
+#[test]
+fn test_mlp_evaluator(){
+    let network = column_network(3, 2.);
+    let str = "\
+                        ...X...\n\
+                        ...O...\n\
+                        ...X...\n\
+                        ...O...\n\
+                        ...X...\n\
+                        ...O...\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+
+    // the full column gets no prior, the others are uniform
+    let (priors, value) = network.evaluate_with_priors(&game, 1);
+    assert_eq!(priors.unwrap(), vec![1. / 6.; 6]);
+    assert!((value - 0.5f32.tanh()).abs() < 0.0001);
+    assert_eq!(network.evaluate(&game, 1), value);
+
+    let (priors, _) = network.evaluate_with_priors(&Connect4::<6,7,4>::empty(), 1);
+    let priors = priors.unwrap();
+    assert!((priors.iter().sum::<f32>() - 1.).abs() < 0.0001);
+    assert!((priors[3] - 2f32.exp() / (6. + 2f32.exp())).abs() < 0.0001);
+}
+
+#[test]
+fn test_mlp_check(){
+    let game = Connect4::<6,7,4>::empty();
+    assert_eq!(column_network(3, 2.).check(&game), Ok(()));
+
+    // a well-formed network with too few policy outputs or the wrong number of inputs is rejected, and evaluates without panicking
+    let layer = |inputs: usize, outputs: usize| Layer { weights: vec![0.5; inputs * outputs], biases: vec![0.; outputs] };
+    for network in [Mlp::new(vec![layer(84, 4)], layer(4, 5), layer(4, 1)).unwrap(), Mlp::new(vec![layer(42, 4)], layer(4, 7), layer(4, 1)).unwrap()] {
+        let network = Mlp::from_bytes(&network.to_bytes()).unwrap();
+        assert!(network.check(&game).is_err());
+        assert_eq!(network.evaluate_with_priors(&game, 1), (None, 0.));
+    }
+}
+
+#[test]
+fn test_mlp_search(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let game = Connect4::<6,7,4>::empty();
+
+    // with the value of every position being even, the search follows the policy
+    for column in [1, 5] {
+        let network = Mlp { value: Layer { weights: vec![0.], biases: vec![0.] }, ..column_network(column, 5.) };
+        let config = SearchConfig { selection: Puct { constant: 5. }, rollout: UniformRollout, leaf: LeafEvaluation { evaluator: network, cutoff: None, weight: 1. }, draw_value: 0., early_stop: Default::default() };
+        let root = Tree::<Connect4<6,7,4>>::ROOT;
+
+        // the first iteration after the expansion of the root goes to the move with the highest prior
+        let mut tree = Tree::new(game.clone());
+        tree.predict(2, 1, &config, &mut rng);
+        assert_eq!(Vec::from_iter(tree.children(root).map(|id| tree.node(id).stats.visits)), Vec::from_iter((0..7).map(|i| (i == column) as usize)));
+        assert!((tree.children(root).map(|id| tree.node(id).stats.prior).sum::<f32>() - 1.).abs() < 0.0001);
+
+        // the evaluations are turned into random results, which the strong prior outweighs
+        tree.predict(100, 1, &config, &mut rng);
+        let best = tree.children(root).start + column;
+        assert!(tree.node(best).stats.visits > tree.root().stats.visits / 2);
+
+        let mut node = Node::new(game.clone(), 0);
+        node.predict(100, 1, &config, &mut rng);
+        assert!(node.children[column].visits > node.visits / 2);
+    }
+}
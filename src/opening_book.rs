@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use super::connect4::Connect4;
+use super::engine::{Budget, Engine};
+use super::mcts::GeneralGame;
+
+/// identifies an opening book file, followed by the version of the format
+const MAGIC: &[u8; 4] = b"BOOK";
+const VERSION: u32 = 1;
+
+/// Moves for the first positions of a game. A position and its mirror image share one entry, so the book only stores
+/// positions whose key is at most the key of their mirror image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpeningBook<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> {
+    /// position keys and columns, sorted by the key
+    entries: Vec<(u64, u8)>
+}
+
+impl<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> Default for OpeningBook<ROWS, COLUMNS, CONNECT> {
+    fn default() -> Self {
+        return OpeningBook::new();
+    }
+}
+
+impl<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> OpeningBook<ROWS, COLUMNS, CONNECT> {
+    pub fn new() -> OpeningBook<ROWS, COLUMNS, CONNECT> {
+        assert!((ROWS + 1) * COLUMNS <= 64, "the board does not fit into 64 bits");
+        return OpeningBook { entries: Vec::new() };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// Unique for every position with `player` to move. Every column takes ROWS + 1 bits, with the tokens of `player` and
+    /// one more bit on top of the highest token. With `mirrored` the columns are numbered from the right.
+    fn key(game: &Connect4<ROWS, COLUMNS, CONNECT>, player: i8, mirrored: bool) -> u64 {
+        let mut key = 0u64;
+
+        for col in 0..COLUMNS {
+            let column = if mirrored { COLUMNS - 1 - col } else { col };
+            let mut height = 0;
+            for row in (0..ROWS).rev() {
+                match game.board[row][column] {
+                    0 => break,
+                    cell if cell == player => key |= 1 << (col * (ROWS + 1) + height),
+                    _ => ()
+                }
+                height += 1;
+            }
+            key |= 1 << (col * (ROWS + 1) + height);
+        }
+
+        return key;
+    }
+
+    /// the key shared with the mirror image and whether the position has to be mirrored to get it
+    fn canonical_key(game: &Connect4<ROWS, COLUMNS, CONNECT>) -> (u64, bool) {
+        let (key, mirrored) = (Self::key(game, game.player(), false), Self::key(game, game.player(), true));
+        return if mirrored < key { (mirrored, true) } else { (key, false) };
+    }
+
+    /// the column for the player to move in `game`, `None` if the position is not in the book
+    pub fn lookup(&self, game: &Connect4<ROWS, COLUMNS, CONNECT>) -> Option<usize> {
+        let (key, mirrored) = Self::canonical_key(game);
+        let index = self.entries.binary_search_by_key(&key, |&(key, _)| key).ok()?;
+
+        let col = self.entries[index].1 as usize;
+        let col = if mirrored { COLUMNS - 1 - col } else { col };
+        if game.board[0][col] != 0 {
+            return None;
+        }
+
+        return Some(col);
+    }
+
+    /// stores `col` as the move of the player to move in `game` and the mirrored column for the mirror image
+    pub fn insert(&mut self, game: &Connect4<ROWS, COLUMNS, CONNECT>, col: usize) {
+        assert!(col < COLUMNS, "the column is outside of the board");
+
+        let (key, mirrored) = Self::canonical_key(game);
+        let col = if mirrored { COLUMNS - 1 - col } else { col } as u8;
+        match self.entries.binary_search_by_key(&key, |&(key, _)| key) {
+            Ok(index) => self.entries[index].1 = col,
+            Err(index) => self.entries.insert(index, (key, col))
+        }
+    }
+
+    /// Asks `engine` for the move in every position reached from `game` by fewer than `plies` moves, mirror images are
+    /// analyzed only once. The number of positions grows about `COLUMNS` times with every ply.
+    pub fn build(game: &Connect4<ROWS, COLUMNS, CONNECT>, plies: usize, engine: &mut dyn Engine<Connect4<ROWS, COLUMNS, CONNECT>>, budget: Budget) -> OpeningBook<ROWS, COLUMNS, CONNECT> {
+        let mut book = OpeningBook::new();
+        book.extend(game, plies, engine, budget);
+        return book;
+    }
+
+    fn extend(&mut self, game: &Connect4<ROWS, COLUMNS, CONNECT>, plies: usize, engine: &mut dyn Engine<Connect4<ROWS, COLUMNS, CONNECT>>, budget: Budget) {
+        if plies == 0 || game.get_outcome().is_over() || self.lookup(game).is_some() {
+            return;
+        }
+        let Some(col) = engine.choose_move(game, budget) else {
+            return;
+        };
+        self.insert(game, col);
+
+        for col in game.get_available() {
+            let mut child = game.clone();
+            child.update(col);
+            self.extend(&child, plies - 1, engine, budget);
+        }
+    }
+
+    /// reads the format written by `to_bytes`, fails if the book was made for another board
+    pub fn from_bytes(bytes: &[u8]) -> Result<OpeningBook<ROWS, COLUMNS, CONNECT>, String> {
+        let take = |start: usize, count: usize| bytes.get(start..start + count).ok_or_else(|| "the opening book is truncated".to_string());
+        let u32_at = |start: usize| take(start, 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize);
+
+        if take(0, 4)? != MAGIC {
+            return Err("not an opening book".to_string());
+        }
+        let version = u32_at(4)?;
+        if version != VERSION as usize {
+            return Err(format!("unsupported opening book version {}", version));
+        }
+        let size = (u32_at(8)?, u32_at(12)?, u32_at(16)?);
+        if size != (ROWS, COLUMNS, CONNECT) {
+            return Err(format!("the opening book is for {}x{} boards with {} in a row", size.0, size.1, size.2));
+        }
+
+        let count = u32_at(20)?;
+        let data = take(24, 9 * count)?;
+        if bytes.len() != 24 + 9 * count {
+            return Err("unexpected data after the opening book".to_string());
+        }
+
+        let entries = Vec::from_iter(data.chunks_exact(9).map(|entry| (u64::from_le_bytes(entry[..8].try_into().unwrap()), entry[8])));
+        if entries.windows(2).any(|pair| pair[0].0 >= pair[1].0) || entries.iter().any(|&(_, col)| col as usize >= COLUMNS) {
+            return Err("the opening book is corrupted".to_string());
+        }
+
+        return Ok(OpeningBook { entries });
+    }
+
+    /// magic, version, rows, columns, connect and the number of entries as u32, then every entry as the u64 key and
+    /// the column as one byte, everything little-endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for size in [VERSION, ROWS as u32, COLUMNS as u32, CONNECT as u32, self.entries.len() as u32] {
+            bytes.extend(size.to_le_bytes());
+        }
+        for &(key, col) in self.entries.iter() {
+            bytes.extend(key.to_le_bytes());
+            bytes.push(col);
+        }
+
+        return bytes;
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<OpeningBook<ROWS, COLUMNS, CONNECT>, String> {
+        let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+        return OpeningBook::from_bytes(&bytes);
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        return std::fs::write(path, self.to_bytes()).map_err(|error| error.to_string());
+    }
+}
+
+
+#[cfg(test)]
+use super::solver::Solver;
+
+#[test]
+fn test_opening_book_mirror(){
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .X...O.\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+    let mirror = Connect4::<6,7,4>::from_string(&str.replace(".X...O.", ".O...X.")).unwrap();
+
+    let mut book = OpeningBook::<6,7,4>::new();
+    book.insert(&game, 2);
+
+    assert_eq!(book.len(), 1);
+    assert_eq!(book.lookup(&game), Some(2));
+    assert_eq!(book.lookup(&mirror), Some(4));
+    assert_eq!(book.lookup(&Connect4::empty()), None);
+
+    // the mirror image replaces the entry
+    book.insert(&mirror, 3);
+    assert_eq!(book.len(), 1);
+    assert_eq!(book.lookup(&game), Some(3));
+
+    // a full column is never returned
+    let mut full = Connect4::<6,7,4>::empty();
+    for row in 0..6 {
+        full.board[row][3] = if row % 2 == 0 { 1 } else { -1 };
+    }
+    book.insert(&full, 3);
+    assert_eq!(book.lookup(&full), None);
+}
+
+#[test]
+fn test_opening_book_bytes(){
+    let mut book = OpeningBook::<6,7,4>::new();
+    book.insert(&Connect4::empty(), 3);
+    book.insert(&Connect4::from_string(".......\n.......\n.......\n.......\n.......\nX......\n").unwrap(), 1);
+
+    let bytes = book.to_bytes();
+    assert_eq!(bytes.len(), 24 + 2 * 9);
+    assert_eq!(OpeningBook::<6,7,4>::from_bytes(&bytes), Ok(book));
+
+    assert!(OpeningBook::<6,7,4>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(OpeningBook::<6,7,4>::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+    assert!(OpeningBook::<6,7,4>::from_bytes(b"MLPW").is_err());
+    assert!(OpeningBook::<7,6,4>::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn test_opening_book_build(){
+    let mut solver = Solver::<4,4,4>::new(1 << 16);
+    let book = OpeningBook::build(&Connect4::<4,4,4>::empty(), 3, &mut solver, Budget::Nodes(0));
+
+    // the empty board, 2 positions after the first move and 8 after the second one, up to mirror images
+    assert_eq!(book.len(), 11);
+
+    let game = Connect4::<4,4,4>::from_string("....\n....\n....\nX..O\n").unwrap();
+    assert_eq!(book.lookup(&game), solver.best_move(&game));
+    assert_eq!(book.lookup(&Connect4::empty()), Some(1));
+
+    let game = Connect4::<4,4,4>::from_string("....\n....\nX...\nX..O\n").unwrap();
+    assert_eq!(book.lookup(&game), None);
+}
@@ -0,0 +1,475 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::thread;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use super::mcts::{backup_proven, most_visited_child, select_child, terminal_value, GeneralGame, Node, Proven, SearchConfig, Stats};
+use super::evaluation::{evaluate_expansion, simulate, Evaluator};
+use super::rollout::RolloutPolicy;
+use super::selection::SelectionPolicy;
+
+/// Root-parallel search, each of the `threads` threads searches its own tree for `iters` iterations and the statistics of
+/// the roots and their children are merged. Only the root and its children of the returned node carry the merged statistics.
+/// Every thread gets its own generator seeded from `rng`.
+pub fn predict_root_parallel<T, S, R, E, G>(game: &T, iters: usize, rollouts: usize, config: &SearchConfig<S, R, E>, threads: usize, rng: &mut G) -> Node<T>
+where T: GeneralGame + Send + Sync, T::Move: Send + Sync, S: SelectionPolicy + Sync, R: RolloutPolicy + Sync, E: Evaluator<T> + Sync, G: Rng + ?Sized {
+    let seeds: Vec<u64> = (0..threads).map(|_| rng.gen()).collect();
+
+    let trees: Vec<Node<T>> = thread::scope(|scope| {
+        let handles: Vec<_> = seeds.iter().map(|&seed| scope.spawn(move || {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut tree = Node::new(game.clone(), T::Move::default());
+            tree.predict(iters, rollouts, config, &mut rng);
+            tree
+        })).collect();
+
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    let mut root = Node::new(game.clone(), T::Move::default());
+    for tree in trees.iter() {
+        root.merge(tree);
+    }
+
+    return root;
+}
+
+
+fn proven_to_u8(proven: Proven) -> u8 {
+    return match proven {
+        Proven::Unknown => 0,
+        Proven::Win => 1,
+        Proven::Loss => 2,
+        Proven::Draw => 3
+    };
+}
+
+fn proven_from_u8(value: u8) -> Proven {
+    return match value {
+        1 => Proven::Win,
+        2 => Proven::Loss,
+        3 => Proven::Draw,
+        _ => Proven::Unknown
+    };
+}
+
+/// Thread-safe counterpart of `Node`, which several threads can search at the same time.
+/// Simulations which are still running count as losses of the nodes they pass through (virtual loss),
+/// so that the threads spread over different lines.
+#[derive(Debug)]
+pub struct SharedNode<T: GeneralGame> {
+    pub game: T,
+    pub player: i8,
+    pub move_index: T::Move,
+    /// prior probability of `move_index`, see `Stats::prior`
+    pub prior: f32,
+    visits: AtomicUsize,
+    wins: AtomicUsize,
+    losses: AtomicUsize,
+    draws: AtomicUsize,
+    amaf_visits: AtomicUsize,
+    amaf_wins: AtomicUsize,
+    amaf_losses: AtomicUsize,
+    amaf_draws: AtomicUsize,
+    virtual_losses: AtomicUsize,
+    proven: AtomicU8,
+    children: OnceLock<Vec<SharedNode<T>>>
+}
+
+impl<T: GeneralGame> SharedNode<T> {
+    pub fn new(game: T, move_index: T::Move) -> SharedNode<T> {
+        return SharedNode {
+            player: game.player(), game, move_index, prior: 0.,
+            visits: AtomicUsize::new(0), wins: AtomicUsize::new(0), losses: AtomicUsize::new(0), draws: AtomicUsize::new(0),
+            amaf_visits: AtomicUsize::new(0), amaf_wins: AtomicUsize::new(0), amaf_losses: AtomicUsize::new(0), amaf_draws: AtomicUsize::new(0),
+            virtual_losses: AtomicUsize::new(0),
+            proven: AtomicU8::new(proven_to_u8(Proven::Unknown)),
+            children: OnceLock::new()
+        };
+    }
+
+    /// statistics of the finished simulations
+    pub fn stats(&self) -> Stats {
+        return Stats {
+            visits: self.visits.load(Ordering::Relaxed),
+            wins: self.wins.load(Ordering::Relaxed),
+            losses: self.losses.load(Ordering::Relaxed),
+            draws: self.draws.load(Ordering::Relaxed),
+            amaf_visits: self.amaf_visits.load(Ordering::Relaxed),
+            amaf_wins: self.amaf_wins.load(Ordering::Relaxed),
+            amaf_losses: self.amaf_losses.load(Ordering::Relaxed),
+            amaf_draws: self.amaf_draws.load(Ordering::Relaxed),
+            prior: self.prior
+        };
+    }
+
+    /// statistics used for the selection, where the running simulations count as losses
+    fn selection_stats(&self) -> Stats {
+        let mut stats = self.stats();
+        stats.losses += self.virtual_losses.load(Ordering::Relaxed);
+        return stats;
+    }
+
+    pub fn proven(&self) -> Proven {
+        return proven_from_u8(self.proven.load(Ordering::Relaxed));
+    }
+
+    fn set_proven(&self, proven: Proven) {
+        self.proven.store(proven_to_u8(proven), Ordering::Relaxed);
+    }
+
+    /// children of the node, empty until the node is expanded
+    pub fn children(&self) -> &[SharedNode<T>] {
+        return match self.children.get() {
+            Some(children) => children,
+            None => &[]
+        };
+    }
+
+    /// children with their priors as in `Node::expand`, returns the evaluation of this node which comes with the priors
+    fn create_children<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>>(&self, config: &SearchConfig<S, R, E>) -> (Vec<SharedNode<T>>, Option<f32>) {
+        // If the game is over, there is no point in creating children
+        if self.game.get_outcome().is_over() {
+            return (Vec::new(), None);
+        }
+
+        let available = self.game.get_available();
+        let prior = 1. / available.len() as f32;
+        let mut children = Vec::from_iter(available.into_iter().map(|index| {
+            let mut game = self.game.clone();
+            game.update(index);
+            let mut child = SharedNode::new(game, index);
+            child.prior = prior;
+            child
+        }));
+
+        let Some((priors, value)) = evaluate_expansion(&self.game, self.player, config) else {
+            return (children, None);
+        };
+        for (child, prior) in children.iter_mut().zip(priors.into_iter().flatten()) {
+            child.prior = prior;
+        }
+
+        return (children, Some(value));
+    }
+
+    fn add_results(&self, wins_1: usize, wins_n1: usize, draws: usize) {
+        let (wins, losses) = if self.player == -1 { (wins_1, wins_n1) } else { (wins_n1, wins_1) };
+        self.wins.fetch_add(wins, Ordering::Relaxed);
+        self.losses.fetch_add(losses, Ordering::Relaxed);
+        self.draws.fetch_add(draws, Ordering::Relaxed);
+    }
+
+    /// same as `Node::propagate`, but can be called from several threads at once
+    pub fn propagate<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&self, rollouts: usize, config: &SearchConfig<S, R, E>, rng: &mut G, moves: &mut Vec<(T::Move, i8)>) -> (usize, usize, usize) {
+        return self.propagate_with(rollouts, config, config.clamped_draw_value(), rng, moves);
+    }
+
+    /// `draw_value` is the value of a draw for the player to move, as in `Node::propagate`
+    fn propagate_with<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&self, rollouts: usize, config: &SearchConfig<S, R, E>, draw_value: f32, rng: &mut G, moves: &mut Vec<(T::Move, i8)>) -> (usize, usize, usize) {
+        self.visits.fetch_add(rollouts, Ordering::Relaxed);
+        self.virtual_losses.fetch_add(rollouts, Ordering::Relaxed);
+
+        let result = self.propagate_inner(rollouts, config, draw_value, rng, moves);
+
+        self.add_results(result.0, result.1, result.2);
+        self.virtual_losses.fetch_sub(rollouts, Ordering::Relaxed);
+
+        return result;
+    }
+
+    fn propagate_inner<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&self, rollouts: usize, config: &SearchConfig<S, R, E>, draw_value: f32, rng: &mut G, moves: &mut Vec<(T::Move, i8)>) -> (usize, usize, usize) {
+        if self.proven() == Proven::Unknown && self.children.get().is_none() {
+            self.set_proven(terminal_value(&self.game, self.player));
+        }
+
+        // if the result is already known, just return it
+        let winner = match self.proven() {
+            Proven::Win => -self.player,
+            Proven::Loss => self.player,
+            Proven::Draw => return (0, 0, rollouts),
+            Proven::Unknown => 0
+        };
+        if winner == 1 { return (rollouts, 0, 0); }
+        if winner == -1 { return (0, rollouts, 0); }
+
+        let (mut wins_1, mut wins_n1, mut draws) = (0usize, 0usize, 0usize);
+        let amaf = config.selection.uses_amaf();
+
+        match self.children.get() {
+            // If the children have not been created yet, do rollouts and initialize children
+            None => {
+                let mut leaf_value = None;
+                self.children.get_or_init(|| {
+                    let (children, value) = self.create_children(config);
+                    leaf_value = value;
+                    children
+                });
+
+                for _ in 0..rollouts {
+                    let res = simulate(&mut self.game.clone(), self.player, config, rng, if amaf { Some(&mut *moves) } else { None }, leaf_value);
+
+                    if res == 1 { wins_1 += 1; }
+                    else if res == -1 { wins_n1 += 1; }
+                    else { draws += 1; }
+                }
+            },
+            // recursively call next children with highest score
+            Some(children) => {
+                let parent_visits = self.visits.load(Ordering::Relaxed);
+                // the statistics have to stay the same during the selection, while other threads keep updating them
+                let snapshot: Vec<(Stats, Proven)> = children.iter().map(|child| (child.selection_stats(), child.proven())).collect();
+                let next = select_child(snapshot.into_iter(), parent_visits, &config.selection, draw_value, rng);
+                if let Some(next_node_index) = next {
+                    (wins_1, wins_n1, draws) = children[next_node_index].propagate_with(rollouts, config, -draw_value, rng, moves);
+                    if amaf {
+                        moves.push((children[next_node_index].move_index, self.player));
+                    }
+                }
+                // same as in `Node::propagate`
+                else {
+                    draws = rollouts;
+                }
+
+                let proven = backup_proven(children.iter().map(|child| child.proven()));
+                if proven != Proven::Unknown {
+                    self.set_proven(proven);
+                }
+            }
+        }
+
+        if amaf {
+            self.update_amaf(moves, wins_1, wins_n1, draws);
+        }
+
+        return (wins_1, wins_n1, draws);
+    }
+
+    fn update_amaf(&self, moves: &[(T::Move, i8)], wins_1: usize, wins_n1: usize, draws: usize) {
+        let (wins, losses) = if self.player == 1 { (wins_1, wins_n1) } else { (wins_n1, wins_1) };
+
+        for child in self.children() {
+            if moves.contains(&(child.move_index, self.player)) {
+                child.amaf_visits.fetch_add(wins + losses + draws, Ordering::Relaxed);
+                child.amaf_wins.fetch_add(wins, Ordering::Relaxed);
+                child.amaf_losses.fetch_add(losses, Ordering::Relaxed);
+                child.amaf_draws.fetch_add(draws, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// same as `Node::get_most_visited_child`
+    pub fn get_most_visited_child(&self) -> Option<&SharedNode<T>> {
+        let children = self.children();
+        return most_visited_child(children.iter().map(|child| (child.stats(), child.proven()))).map(|index| &children[index]);
+    }
+}
+
+/// Tree-parallel search, `threads` threads run `iters` iterations in total on the shared tree of `root`.
+/// Stops early once the value of the root is proven, returns the number of iterations performed.
+/// Every thread gets its own generator seeded from `rng`, the result still depends on the scheduling of the threads.
+pub fn predict_tree_parallel<T, S, R, E, G>(root: &SharedNode<T>, iters: usize, rollouts: usize, config: &SearchConfig<S, R, E>, threads: usize, rng: &mut G) -> usize
+where T: GeneralGame + Send + Sync, T::Move: Send + Sync, S: SelectionPolicy + Sync, R: RolloutPolicy + Sync, E: Evaluator<T> + Sync, G: Rng + ?Sized {
+    let started = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let seeds: Vec<u64> = (0..threads).map(|_| rng.gen()).collect();
+
+    thread::scope(|scope| {
+        for &seed in seeds.iter() {
+            let (started, finished) = (&started, &finished);
+            scope.spawn(move || {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut moves = Vec::new();
+
+                while root.proven() == Proven::Unknown && started.fetch_add(1, Ordering::Relaxed) < iters {
+                    moves.clear();
+                    root.propagate(rollouts, config, &mut rng, &mut moves);
+                    finished.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    return finished.load(Ordering::Relaxed);
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::{Rave, Ucb1};
+#[cfg(test)]
+use super::connect4::Connect4;
+
+#[test]
+fn test_predict_root_parallel(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let game = TicTacToe::from_string("...\n...\n...").unwrap();
+
+    let root = predict_root_parallel(&game, 100, 1, &config, 4, &mut rng);
+    assert_eq!(root.visits, 400);
+    assert_eq!(root.children.len(), 9);
+    // the first iteration of every tree only expands the root
+    assert_eq!(root.children.iter().map(|child| child.visits).sum::<usize>(), 396);
+}
+
+#[test]
+fn test_predict_root_parallel_best_move(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        O.....X\n\
+                        O.....X\n\
+                        O.....X\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+
+    let root = predict_root_parallel(&game, 200, 1, &config, 4, &mut rng);
+    assert_eq!(root.get_most_visited_child().unwrap().move_index, 6);
+}
+
+#[test]
+fn test_shared_node_propagate(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let root = SharedNode::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+
+    assert_eq!(predict_tree_parallel(&root, 1000, 1, &config, 4, &mut rng), 1000);
+
+    let stats = root.stats();
+    assert_eq!(stats.visits, 1000);
+    assert_eq!(stats.visits, stats.wins + stats.losses + stats.draws);
+    assert_eq!(root.children().len(), 9);
+    // several threads can expand the root at the same time at the start
+    let child_visits = root.children().iter().map(|child| child.stats().visits).sum::<usize>();
+    assert!((996..=999).contains(&child_visits));
+    // all virtual losses are removed once the simulations finish
+    assert_eq!(root.virtual_losses.load(Ordering::Relaxed), 0);
+    assert!(root.children().iter().all(|child| child.virtual_losses.load(Ordering::Relaxed) == 0));
+
+    let config = SearchConfig::<Rave, UniformRollout>::default();
+    let root = SharedNode::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    predict_tree_parallel(&root, 1000, 1, &config, 4, &mut rng);
+    assert!(root.children().iter().all(|child| child.stats().amaf_visits >= child.stats().visits));
+}
+
+#[test]
+fn test_shared_node_virtual_loss(){
+    let root = SharedNode::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    root.visits.store(10, Ordering::Relaxed);
+    root.wins.store(5, Ordering::Relaxed);
+    root.virtual_losses.store(3, Ordering::Relaxed);
+
+    assert_eq!(root.stats().losses, 0);
+    assert_eq!(root.selection_stats().losses, 3);
+    assert!(root.selection_stats().value(0.) < root.stats().value(0.));
+}
+
+#[test]
+fn test_shared_node_proven(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    let root = SharedNode::new(TicTacToe::from_string("XX.\nOO.\n...").unwrap(), Square::default());
+    let iters = predict_tree_parallel(&root, 10000, 1, &config, 4, &mut rng);
+    assert!(iters < 10000);
+    assert_eq!(root.proven(), Proven::Loss);
+    assert_eq!(root.get_most_visited_child().unwrap().move_index, Square(0,2));
+}
+
+#[test]
+fn test_tree_parallel_matches_sequential(){
+    const ITERS: usize = 5000;
+    const SEEDS: u64 = 8;
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let game = TicTacToe::from_string("...\n...\n...").unwrap();
+
+    // averaged over several seeds, the value of the root and the share of visits of every move are close
+    let mut sequential_value = 0.;
+    let mut shared_value = 0.;
+    let mut sequential_shares = [0.; 9];
+    let mut shared_shares = [0.; 9];
+    for seed in 0..SEEDS {
+        let mut sequential = Node::new(game.clone(), Square::default());
+        sequential.predict(ITERS, 1, &config, &mut StdRng::seed_from_u64(seed));
+        sequential_value += sequential.value(0.) / SEEDS as f32;
+        for (share, child) in sequential_shares.iter_mut().zip(sequential.children.iter()) {
+            *share += child.visits as f32 / (ITERS * SEEDS as usize) as f32;
+        }
+
+        let shared = SharedNode::new(game.clone(), Square::default());
+        assert_eq!(predict_tree_parallel(&shared, ITERS, 1, &config, 4, &mut StdRng::seed_from_u64(seed)), ITERS);
+        shared_value += shared.stats().value(0.) / SEEDS as f32;
+        for (share, (child, sequential_child)) in shared_shares.iter_mut().zip(shared.children().iter().zip(sequential.children.iter())) {
+            assert_eq!(child.move_index, sequential_child.move_index);
+            *share += child.stats().visits as f32 / (ITERS * SEEDS as usize) as f32;
+        }
+    }
+
+    assert!((sequential_value - shared_value).abs() < 0.05, "root value: {} vs {}", sequential_value, shared_value);
+    for (sequential_share, shared_share) in sequential_shares.iter().zip(shared_shares.iter()) {
+        assert!((sequential_share - shared_share).abs() < 0.06, "visit shares: {:?} vs {:?}", sequential_shares, shared_shares);
+    }
+
+    // with one thread the shared tree makes the same decisions as `Node` with the seed of the thread
+    let seed: u64 = StdRng::seed_from_u64(0).gen();
+    let mut sequential = Node::new(game.clone(), Square::default());
+    sequential.predict(ITERS, 1, &config, &mut StdRng::seed_from_u64(seed));
+
+    let shared = SharedNode::new(game, Square::default());
+    assert_eq!(predict_tree_parallel(&shared, ITERS, 1, &config, 1, &mut StdRng::seed_from_u64(0)), ITERS);
+
+    assert_eq!(shared.stats(), sequential.stats());
+    for (child, sequential_child) in shared.children().iter().zip(sequential.children.iter()) {
+        assert_eq!(child.move_index, sequential_child.move_index);
+        assert_eq!(child.stats(), sequential_child.stats());
+    }
+
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ......X\n\
+                        O.....X\n\
+                        O.....X\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+    let shared = SharedNode::new(game, 0);
+    predict_tree_parallel(&shared, 2000, 1, &config, 4, &mut StdRng::seed_from_u64(0));
+    assert_eq!(shared.get_most_visited_child().unwrap().move_index, 6);
+}
+
+// depends on the number of cores and the load of the machine, run with `cargo test --release --features parallel -- --ignored --nocapture test_tree_parallel_scaling`
+#[test]
+#[ignore]
+fn test_tree_parallel_scaling(){
+    let mut rng = StdRng::seed_from_u64(0);
+    const ITERS: usize = 20000;
+
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(4);
+    if threads < 2 {
+        println!("skipped, only 1 core available");
+        return;
+    }
+
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut time = |threads: usize| {
+        let root = SharedNode::new(Connect4::<6,7,4>::empty(), 0);
+        let start = std::time::Instant::now();
+        predict_tree_parallel(&root, ITERS, 1, &config, threads, &mut rng);
+        start.elapsed().as_secs_f64()
+    };
+
+    let single = time(1);
+    let parallel = time(threads);
+    println!("1 thread: {:.3}s, {} threads: {:.3}s", single, threads, parallel);
+    assert!(single / parallel > threads as f64 / 2.);
+}
@@ -0,0 +1,249 @@
+use std::fmt::Display;
+
+use super::mcts::{GeneralGame, Node, Proven};
+use super::tree::Tree;
+
+/// Statistics of one move at the root, from the point of view of the player to move
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveReport<M> {
+    pub move_index: M,
+    pub visits: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub value: f32,
+    pub proven: Proven
+}
+
+/// Summary of a search, for showing the evaluation and the intended line of the bot
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchReport<M> {
+    pub moves: Vec<MoveReport<M>>,
+    /// moves following the most visited child from the root
+    pub principal_variation: Vec<M>,
+    /// depth of the deepest visited node, the root has depth 0
+    pub max_depth: usize,
+    /// average depth of the visited nodes without visited children
+    pub average_depth: f32,
+    /// number of nodes in the tree, including children which were created but not visited yet
+    pub node_count: usize,
+    /// bytes allocated by the tree
+    pub memory_bytes: usize,
+    pub iterations: usize
+}
+
+fn proven_name(proven: Proven) -> &'static str {
+    return match proven {
+        Proven::Unknown => "unknown",
+        Proven::Win => "win",
+        Proven::Loss => "loss",
+        Proven::Draw => "draw"
+    };
+}
+
+impl<M: Copy> SearchReport<M> {
+    /// `iterations` is the number of iterations the search performed, values of draws are `draw_value`
+    pub fn from_node<T: GeneralGame<Move = M>>(root: &Node<T>, iterations: usize, draw_value: f32) -> SearchReport<M> {
+        let moves = Vec::from_iter(root.children.iter().map(|child| MoveReport {
+            move_index: child.move_index,
+            visits: child.visits,
+            wins: child.wins,
+            losses: child.losses,
+            draws: child.draws,
+            value: child.value(draw_value),
+            proven: child.proven
+        }));
+
+        let mut principal_variation = Vec::new();
+        let mut node = root;
+        while let Some(child) = node.get_most_visited_child() {
+            principal_variation.push(child.move_index);
+            node = child;
+        }
+
+        let mut node_count = 0usize;
+        let mut memory_bytes = std::mem::size_of::<Node<T>>();
+        let mut max_depth = 0usize;
+        let (mut leaf_count, mut leaf_depths) = (0usize, 0usize);
+
+        let mut stack = vec![(root, 0usize)];
+        while let Some((node, depth)) = stack.pop() {
+            node_count += 1;
+            memory_bytes += node.children.capacity() * std::mem::size_of::<Node<T>>();
+            if node.visits == 0 {
+                continue;
+            }
+
+            max_depth = max_depth.max(depth);
+            if node.children.iter().all(|child| child.visits == 0) {
+                leaf_count += 1;
+                leaf_depths += depth;
+            }
+
+            stack.extend(node.children.iter().map(|child| (child, depth + 1)));
+        }
+
+        let average_depth = if leaf_count == 0 { 0. } else { leaf_depths as f32 / leaf_count as f32 };
+
+        return SearchReport { moves, principal_variation, max_depth, average_depth, node_count, memory_bytes, iterations };
+    }
+
+    /// same as `from_node`, for the root of `tree`
+    pub fn from_tree<T: GeneralGame<Move = M>>(tree: &Tree<T>, iterations: usize, draw_value: f32) -> SearchReport<M> {
+        let root = Tree::<T>::ROOT;
+        let moves = Vec::from_iter(tree.children(root).map(|id| {
+            let child = tree.node(id);
+            MoveReport {
+                move_index: child.move_index,
+                visits: child.stats.visits,
+                wins: child.stats.wins,
+                losses: child.stats.losses,
+                draws: child.stats.draws,
+                value: child.value(draw_value),
+                proven: child.proven
+            }
+        }));
+
+        let mut principal_variation = Vec::new();
+        let mut id = root;
+        while let Some(child) = tree.get_most_visited_child(id) {
+            principal_variation.push(tree.node(child).move_index);
+            id = child;
+        }
+
+        let mut max_depth = 0usize;
+        let (mut leaf_count, mut leaf_depths) = (0usize, 0usize);
+
+        let mut stack = vec![(root, 0usize)];
+        while let Some((id, depth)) = stack.pop() {
+            if tree.node(id).stats.visits == 0 {
+                continue;
+            }
+
+            max_depth = max_depth.max(depth);
+            if tree.children(id).all(|child| tree.node(child).stats.visits == 0) {
+                leaf_count += 1;
+                leaf_depths += depth;
+            }
+
+            stack.extend(tree.children(id).map(|child| (child, depth + 1)));
+        }
+
+        let average_depth = if leaf_count == 0 { 0. } else { leaf_depths as f32 / leaf_count as f32 };
+
+        return SearchReport { moves, principal_variation, max_depth, average_depth, node_count: tree.node_count(), memory_bytes: tree.memory_usage(), iterations };
+    }
+}
+
+impl<M: Display> SearchReport<M> {
+    /// moves are written as strings with their `Display`
+    pub fn to_json(&self) -> String {
+        let moves: Vec<String> = self.moves.iter().map(|m| format!(
+            "{{\"move\":\"{}\",\"visits\":{},\"wins\":{},\"losses\":{},\"draws\":{},\"value\":{},\"proven\":\"{}\"}}",
+            m.move_index, m.visits, m.wins, m.losses, m.draws, m.value, proven_name(m.proven)
+        )).collect();
+        let principal_variation: Vec<String> = self.principal_variation.iter().map(|m| format!("\"{}\"", m)).collect();
+
+        return format!(
+            "{{\"iterations\":{},\"node_count\":{},\"memory_bytes\":{},\"max_depth\":{},\"average_depth\":{},\"principal_variation\":[{}],\"moves\":[{}]}}",
+            self.iterations, self.node_count, self.memory_bytes, self.max_depth, self.average_depth, principal_variation.join(","), moves.join(",")
+        );
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::mcts::SearchConfig;
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::Ucb1;
+#[cfg(test)]
+use super::tree::TreeNode;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+#[test]
+fn test_report_from_node(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+    let iterations = node.predict(500, 1, &config, &mut rng);
+
+    let report = SearchReport::from_node(&node, iterations, 0.);
+    assert_eq!(report.iterations, 500);
+    assert_eq!(report.moves.len(), 9);
+    assert_eq!(report.moves.iter().map(|m| m.visits).sum::<usize>(), 499);
+    for m in report.moves.iter() {
+        assert_eq!(m.visits, m.wins + m.losses + m.draws);
+        assert!((-1. ..=1.).contains(&m.value));
+    }
+
+    // the principal variation starts with the most visited move and is a legal sequence of moves
+    assert_eq!(report.principal_variation[0], node.get_most_visited_child().unwrap().move_index);
+    let mut game = TicTacToe::from_string("...\n...\n...").unwrap();
+    for &index in report.principal_variation.iter() {
+        assert!(GeneralGame::get_available(&game).contains(&index));
+        GeneralGame::update(&mut game, index);
+    }
+
+    assert!(report.max_depth >= report.principal_variation.len());
+    assert!(report.average_depth >= 1. && report.average_depth <= report.max_depth as f32);
+    // root, 9 children and at least the children of the most visited child
+    assert!(report.node_count > 18);
+}
+
+#[test]
+fn test_report_depth(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut node = Node::new(TicTacToe::from_string("...\n...\n...").unwrap(), Square::default());
+
+    let report = SearchReport::from_node(&node, 0, 0.);
+    assert_eq!(report, SearchReport { moves: vec![], principal_variation: vec![], max_depth: 0, average_depth: 0., node_count: 1, memory_bytes: std::mem::size_of::<Node<TicTacToe>>(), iterations: 0 });
+
+    // after two iterations the root and one child were visited
+    node.predict(2, 1, &config, &mut rng);
+    let report = SearchReport::from_node(&node, 2, 0.);
+    assert_eq!(report.max_depth, 1);
+    assert_eq!(report.average_depth, 1.);
+    assert_eq!(report.node_count, 1 + 9 + 8);
+    assert_eq!(report.principal_variation.len(), 1);
+}
+
+#[test]
+fn test_report_from_tree(){
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let game = TicTacToe::from_string("...\n...\n...").unwrap();
+
+    // the same search on both trees gives the same report, except for the memory
+    let mut node = Node::new(game.clone(), Square::default());
+    node.predict(500, 1, &config, &mut StdRng::seed_from_u64(1));
+    let mut tree = Tree::new(game);
+    tree.predict(500, 1, &config, &mut StdRng::seed_from_u64(1));
+
+    let node_report = SearchReport::from_node(&node, 500, 0.);
+    let tree_report = SearchReport::from_tree(&tree, 500, 0.);
+    assert_eq!(tree_report, SearchReport { memory_bytes: tree_report.memory_bytes, ..node_report.clone() });
+    assert!(tree_report.memory_bytes >= tree.node_count() * std::mem::size_of::<TreeNode<Square>>());
+}
+
+#[test]
+fn test_report_json(){
+    let report = SearchReport {
+        moves: vec![MoveReport { move_index: "d1", visits: 10, wins: 5, losses: 3, draws: 2, value: 0.2, proven: Proven::Unknown },
+                    MoveReport { move_index: "e1", visits: 2, wins: 2, losses: 0, draws: 0, value: 1., proven: Proven::Win }],
+        principal_variation: vec!["d1", "b2"],
+        max_depth: 4,
+        average_depth: 2.5,
+        node_count: 30,
+        memory_bytes: 2400,
+        iterations: 12
+    };
+
+    assert_eq!(report.to_json(), "{\"iterations\":12,\"node_count\":30,\"memory_bytes\":2400,\"max_depth\":4,\"average_depth\":2.5,\"principal_variation\":[\"d1\",\"b2\"],\"moves\":[\
+        {\"move\":\"d1\",\"visits\":10,\"wins\":5,\"losses\":3,\"draws\":2,\"value\":0.2,\"proven\":\"unknown\"},\
+        {\"move\":\"e1\",\"visits\":2,\"wins\":2,\"losses\":0,\"draws\":0,\"value\":1,\"proven\":\"win\"}]}");
+}
@@ -0,0 +1,202 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use super::mcts::GeneralGame;
+
+/// Picks the moves played during a rollout
+pub trait RolloutPolicy {
+    /// returns the move `player` makes in `game`, `available` is never empty
+    fn choose<T: GeneralGame, G: Rng + ?Sized>(&self, game: &T, player: i8, available: &[T::Move], rng: &mut G) -> T::Move;
+}
+
+/// Plays uniformly random moves
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UniformRollout;
+
+impl RolloutPolicy for UniformRollout {
+    fn choose<T: GeneralGame, G: Rng + ?Sized>(&self, _game: &T, _player: i8, available: &[T::Move], rng: &mut G) -> T::Move {
+        return *available.choose(rng).unwrap();
+    }
+}
+
+/// Takes an immediate win if there is one, otherwise blocks an immediate win of the opponent, otherwise plays randomly
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TacticalRollout;
+
+impl RolloutPolicy for TacticalRollout {
+    fn choose<T: GeneralGame, G: Rng + ?Sized>(&self, game: &T, player: i8, available: &[T::Move], rng: &mut G) -> T::Move {
+        if let Some(&index) = game.winning_moves(player).choose(rng) {
+            return index;
+        }
+        if let Some(&index) = game.winning_moves(-player).choose(rng) {
+            return index;
+        }
+
+        return *available.choose(rng).unwrap();
+    }
+}
+
+/// Plays moves with probability proportional to `GeneralGame::move_weight`, e.g. preferring the central columns in connect4
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WeightedRollout;
+
+impl RolloutPolicy for WeightedRollout {
+    fn choose<T: GeneralGame, G: Rng + ?Sized>(&self, game: &T, player: i8, available: &[T::Move], rng: &mut G) -> T::Move {
+        return *available.choose_weighted(rng, |&index| game.move_weight(index, player)).unwrap();
+    }
+}
+
+/// Plays moves with probability proportional to `exp(GeneralGame::move_heuristic / temperature)`, a temperature which is not
+/// positive (or NaN) plays the best move
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftmaxRollout {
+    pub temperature: f32
+}
+
+impl Default for SoftmaxRollout {
+    fn default() -> SoftmaxRollout {
+        return SoftmaxRollout { temperature: 1. };
+    }
+}
+
+impl RolloutPolicy for SoftmaxRollout {
+    fn choose<T: GeneralGame, G: Rng + ?Sized>(&self, game: &T, player: i8, available: &[T::Move], rng: &mut G) -> T::Move {
+        let heuristics: Vec<f32> = available.iter().map(|&index| game.move_heuristic(index, player)).collect();
+        let temperature = if self.temperature > 0. { self.temperature } else { f32::MIN_POSITIVE };
+
+        // subtract the maximum to avoid overflow
+        let max = heuristics.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let weights: Vec<(T::Move, f32)> = available.iter().zip(heuristics.iter()).map(|(&index, &h)| (index, ((h - max) / temperature).exp())).collect();
+
+        return weights.choose_weighted(rng, |(_, weight)| *weight).unwrap().0;
+    }
+}
+
+/// Any of the rollout policies, so that the policy can be picked at runtime
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rollout {
+    Uniform(UniformRollout),
+    Tactical(TacticalRollout),
+    Weighted(WeightedRollout),
+    Softmax(SoftmaxRollout)
+}
+
+impl Rollout {
+    /// takes one of "uniform", "tactical", "weighted", "softmax"
+    pub fn from_name(name: &str) -> Option<Rollout> {
+        return match name {
+            "uniform" => Some(Rollout::Uniform(UniformRollout)),
+            "tactical" => Some(Rollout::Tactical(TacticalRollout)),
+            "weighted" => Some(Rollout::Weighted(WeightedRollout)),
+            "softmax" => Some(Rollout::Softmax(SoftmaxRollout::default())),
+            _ => None
+        };
+    }
+}
+
+impl Default for Rollout {
+    fn default() -> Rollout {
+        return Rollout::Uniform(UniformRollout);
+    }
+}
+
+impl RolloutPolicy for Rollout {
+    fn choose<T: GeneralGame, G: Rng + ?Sized>(&self, game: &T, player: i8, available: &[T::Move], rng: &mut G) -> T::Move {
+        return match self {
+            Rollout::Uniform(policy) => policy.choose(game, player, available, rng),
+            Rollout::Tactical(policy) => policy.choose(game, player, available, rng),
+            Rollout::Weighted(policy) => policy.choose(game, player, available, rng),
+            Rollout::Softmax(policy) => policy.choose(game, player, available, rng)
+        };
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::connect4::Connect4;
+
+#[test]
+fn test_uniform_rollout(){
+    let mut rng = rand::thread_rng();
+    let game = TicTacToe::from_string("XX.\nOO.\n...").unwrap();
+    let available = GeneralGame::get_available(&game);
+
+    for _ in 0..100 {
+        let index = UniformRollout.choose(&game, 1, &available, &mut rng);
+        assert!(available.contains(&index));
+    }
+}
+
+#[test]
+fn test_tactical_rollout(){
+    let mut rng = rand::thread_rng();
+
+    // take the win
+    let game = TicTacToe::from_string("XX.\nOO.\n...").unwrap();
+    let available = GeneralGame::get_available(&game);
+    for _ in 0..100 {
+        assert_eq!(TacticalRollout.choose(&game, 1, &available, &mut rng), Square(0,2));
+        assert_eq!(TacticalRollout.choose(&game, -1, &available, &mut rng), Square(1,2));
+    }
+
+    // block the win of the opponent
+    let game = TicTacToe::from_string("XX.\nO..\n...").unwrap();
+    let available = GeneralGame::get_available(&game);
+    for _ in 0..100 {
+        assert_eq!(TacticalRollout.choose(&game, -1, &available, &mut rng), Square(0,2));
+    }
+}
+
+#[test]
+fn test_weighted_rollout(){
+    let mut rng = rand::thread_rng();
+    let game = Connect4::<6,7,4>::empty();
+    let available = game.get_available();
+
+    let mut counts = [0usize; 7];
+    for _ in 0..1600 {
+        counts[WeightedRollout.choose(&game, 1, &available, &mut rng)] += 1;
+    }
+    // the central column has weight 4 and the edge columns weight 1
+    assert!(counts[3] > 2*counts[0]);
+    assert!(counts[3] > 2*counts[6]);
+}
+
+#[test]
+fn test_softmax_rollout(){
+    let mut rng = rand::thread_rng();
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        O......\n\
+                        O......\n\
+                        O.XX...\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string_unchecked(str).unwrap();
+    let available = game.get_available();
+
+    let cold = SoftmaxRollout { temperature: 0.01 };
+    for _ in 0..100 {
+        assert_eq!(cold.choose(&game, -1, &available, &mut rng), 0);
+    }
+
+    for temperature in [0., -1., f32::NAN] {
+        assert_eq!(SoftmaxRollout { temperature }.choose(&game, -1, &available, &mut rng), 0);
+    }
+
+    let hot = SoftmaxRollout { temperature: 1000. };
+    let moves: Vec<usize> = (0..200).map(|_| hot.choose(&game, -1, &available, &mut rng)).collect();
+    assert!(moves.iter().any(|&index| index != 0));
+}
+
+#[test]
+fn test_rollout_from_name(){
+    assert_eq!(Rollout::from_name("uniform"), Some(Rollout::Uniform(UniformRollout)));
+    assert_eq!(Rollout::from_name("tactical"), Some(Rollout::Tactical(TacticalRollout)));
+    assert_eq!(Rollout::from_name("weighted"), Some(Rollout::Weighted(WeightedRollout)));
+    assert_eq!(Rollout::from_name("softmax"), Some(Rollout::Softmax(SoftmaxRollout { temperature: 1. })));
+    assert_eq!(Rollout::from_name("random"), None);
+}
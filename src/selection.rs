@@ -0,0 +1,461 @@
+use rand::Rng;
+use rand_distr::{Beta, Distribution};
+
+use super::mcts::Stats;
+
+/// Decides which child is visited next during the tree descent, the child with the highest score is selected.
+/// Rewards are from the point of view of the player who made the move leading to the child, 1 for a win, -1 for a loss
+/// and `draw_value` for a draw.
+pub trait SelectionPolicy {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, rng: &mut G) -> f32;
+
+    /// whether the search has to collect the all-moves-as-first statistics for this policy
+    fn uses_amaf(&self) -> bool {
+        return false;
+    }
+
+    /// whether the policy scores children by their priors, including children which were not visited yet
+    fn uses_priors(&self) -> bool {
+        return false;
+    }
+}
+
+/// returns the average reward of the node scaled to [0, 1]
+fn mean_01(node: &Stats, draw_value: f32) -> f32 {
+    return (node.value(draw_value) + 1.) / 2.;
+}
+
+/// Upper confidence bound, `mean + constant * sqrt(ln(parent_visits) / visits)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ucb1 {
+    pub constant: f32
+}
+
+impl Default for Ucb1 {
+    // the constant the search always used, written out so that the scores stay exactly the same
+    #[allow(clippy::approx_constant)]
+    fn default() -> Ucb1 {
+        return Ucb1 { constant: 1.4142*2. };
+    }
+}
+
+impl SelectionPolicy for Ucb1 {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, _rng: &mut G) -> f32 {
+        if child.visits == 0 {
+            return f32::INFINITY;
+        }
+
+        let fvisits = child.visits as f32;
+        let fparent_visits = parent_visits as f32;
+
+        return child.value(draw_value) + self.constant * (fparent_visits.ln() / fvisits).sqrt();
+    }
+}
+
+/// UCB1-Tuned, which replaces the constant of UCB1 by an upper bound on the variance of the rewards
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ucb1Tuned;
+
+impl SelectionPolicy for Ucb1Tuned {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, _rng: &mut G) -> f32 {
+        if child.visits == 0 {
+            return f32::INFINITY;
+        }
+
+        let fvisits = child.visits as f32;
+        let log_parent = (parent_visits as f32).ln();
+
+        // rewards scaled to [0, 1]
+        let mean = mean_01(child, draw_value);
+        let draw_01 = (draw_value + 1.) / 2.;
+        let mean_squares = (child.wins as f32 + draw_01 * draw_01 * child.draws as f32) / fvisits;
+        let variance = (mean_squares - mean*mean).max(0.) + (2. * log_parent / fvisits).sqrt();
+
+        let score = mean + (log_parent / fvisits * variance.min(0.25)).sqrt();
+
+        return 2.*score - 1.;
+    }
+}
+
+/// KL-UCB, the upper bound is the largest mean within `ln(N) + c*ln(ln(N))` Kullback-Leibler divergence of the observed mean
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KlUcb {
+    pub c: f32
+}
+
+/// Kullback-Leibler divergence of two Bernoulli distributions
+fn bernoulli_kl(p: f32, q: f32) -> f32 {
+    const EPS: f32 = 1e-6;
+    let p = p.clamp(EPS, 1.-EPS);
+    let q = q.clamp(EPS, 1.-EPS);
+
+    return p * (p/q).ln() + (1.-p) * ((1.-p)/(1.-q)).ln();
+}
+
+impl SelectionPolicy for KlUcb {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, _rng: &mut G) -> f32 {
+        const ITERS: usize = 20;
+
+        if child.visits == 0 {
+            return f32::INFINITY;
+        }
+
+        let log_parent = (parent_visits as f32).ln().max(0.);
+        let bound = (log_parent + self.c * log_parent.ln().max(0.)) / child.visits as f32;
+
+        // bisection for the largest q with kl(mean, q) <= bound
+        let mean = mean_01(child, draw_value);
+        let (mut low, mut high) = (mean, 1f32);
+        for _ in 0..ITERS {
+            let mid = (low + high) / 2.;
+            if bernoulli_kl(mean, mid) <= bound {
+                low = mid;
+            }
+            else {
+                high = mid;
+            }
+        }
+
+        return 2.*low - 1.;
+    }
+}
+
+/// Thompson sampling, the score is sampled from the Beta posterior of the mean reward
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Thompson;
+
+impl SelectionPolicy for Thompson {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, _parent_visits: usize, draw_value: f32, rng: &mut G) -> f32 {
+        // a draw counts partially as a win and partially as a loss
+        let draw_01 = (draw_value + 1.) / 2.;
+        let alpha = 1. + child.wins as f32 + draw_01 * child.draws as f32;
+        let beta = 1. + child.losses as f32 + (1. - draw_01) * child.draws as f32;
+
+        // both parameters are at least 1 for a draw value between -1 and 1, anything else is floored to keep them valid
+        let Ok(distribution) = Beta::new(alpha.max(f32::EPSILON), beta.max(f32::EPSILON)) else { return 0. };
+        let sample = distribution.sample(rng);
+
+        return 2.*sample - 1.;
+    }
+}
+
+/// How much weight the all-moves-as-first value gets compared to the value of the node itself
+#[derive(Debug, Clone, PartialEq)]
+pub enum RaveSchedule {
+    /// `sqrt(k / (3*visits + k))`, both values have equal weight after `k` visits
+    Equivalence { k: f32 },
+    /// `amaf_visits / (visits + amaf_visits + 4*bias^2*visits*amaf_visits)`, minimizes the mean squared error
+    /// assuming the all-moves-as-first value is off by `bias`
+    MinimumMse { bias: f32 }
+}
+
+impl RaveSchedule {
+    /// takes "equivalence" with `parameter` being `k`, or "minimum-mse" with `parameter` being the bias
+    pub fn from_name(name: &str, parameter: f32) -> Option<RaveSchedule> {
+        return match name {
+            "equivalence" => Some(RaveSchedule::Equivalence { k: parameter }),
+            "minimum-mse" => Some(RaveSchedule::MinimumMse { bias: parameter }),
+            _ => None
+        };
+    }
+
+    pub fn beta(&self, visits: usize, amaf_visits: usize) -> f32 {
+        let fvisits = visits as f32;
+        let famaf_visits = amaf_visits as f32;
+
+        return match self {
+            RaveSchedule::Equivalence { k } => (k / (3.*fvisits + k)).sqrt(),
+            RaveSchedule::MinimumMse { bias } => {
+                if amaf_visits == 0 {
+                    return 0.;
+                }
+                famaf_visits / (fvisits + famaf_visits + 4. * bias * bias * fvisits * famaf_visits)
+            }
+        };
+    }
+}
+
+/// Rapid action value estimation, UCB1 where the value is blended with the all-moves-as-first value of the move
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rave {
+    pub constant: f32,
+    pub schedule: RaveSchedule
+}
+
+impl Default for Rave {
+    fn default() -> Rave {
+        return Rave { constant: std::f32::consts::SQRT_2, schedule: RaveSchedule::Equivalence { k: 500. } };
+    }
+}
+
+impl SelectionPolicy for Rave {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, _rng: &mut G) -> f32 {
+        if child.visits == 0 {
+            return f32::INFINITY;
+        }
+
+        let beta = if child.amaf_visits == 0 { 0. } else { self.schedule.beta(child.visits, child.amaf_visits) };
+        let value = (1. - beta) * child.value(draw_value) + beta * child.amaf_value(draw_value);
+
+        return value + self.constant * ((parent_visits as f32).ln() / child.visits as f32).sqrt();
+    }
+
+    fn uses_amaf(&self) -> bool {
+        return true;
+    }
+}
+
+/// Predictor + UCB as in AlphaZero, `value + constant * prior * sqrt(parent_visits) / (1 + visits)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puct {
+    pub constant: f32
+}
+
+impl Default for Puct {
+    fn default() -> Puct {
+        return Puct { constant: 2. };
+    }
+}
+
+impl SelectionPolicy for Puct {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, _rng: &mut G) -> f32 {
+        return child.value(draw_value) + self.constant * child.prior * (parent_visits as f32).sqrt() / (1. + child.visits as f32);
+    }
+
+    fn uses_priors(&self) -> bool {
+        return true;
+    }
+}
+
+/// Any of the selection policies, so that the policy can be picked at runtime
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Ucb1(Ucb1),
+    Ucb1Tuned(Ucb1Tuned),
+    KlUcb(KlUcb),
+    Thompson(Thompson),
+    Rave(Rave),
+    Puct(Puct)
+}
+
+impl Selection {
+    /// takes one of "ucb1", "ucb1-tuned", "kl-ucb", "thompson", "rave", "puct"
+    pub fn from_name(name: &str) -> Option<Selection> {
+        return match name {
+            "ucb1" => Some(Selection::Ucb1(Ucb1::default())),
+            "ucb1-tuned" => Some(Selection::Ucb1Tuned(Ucb1Tuned)),
+            "kl-ucb" => Some(Selection::KlUcb(KlUcb::default())),
+            "thompson" => Some(Selection::Thompson(Thompson)),
+            "rave" => Some(Selection::Rave(Rave::default())),
+            "puct" => Some(Selection::Puct(Puct::default())),
+            _ => None
+        };
+    }
+}
+
+impl Default for Selection {
+    fn default() -> Selection {
+        return Selection::Ucb1(Ucb1::default());
+    }
+}
+
+impl SelectionPolicy for Selection {
+    fn score<G: Rng + ?Sized>(&self, child: &Stats, parent_visits: usize, draw_value: f32, rng: &mut G) -> f32 {
+        return match self {
+            Selection::Ucb1(policy) => policy.score(child, parent_visits, draw_value, rng),
+            Selection::Ucb1Tuned(policy) => policy.score(child, parent_visits, draw_value, rng),
+            Selection::KlUcb(policy) => policy.score(child, parent_visits, draw_value, rng),
+            Selection::Thompson(policy) => policy.score(child, parent_visits, draw_value, rng),
+            Selection::Rave(policy) => policy.score(child, parent_visits, draw_value, rng),
+            Selection::Puct(policy) => policy.score(child, parent_visits, draw_value, rng)
+        };
+    }
+
+    fn uses_amaf(&self) -> bool {
+        return match self {
+            Selection::Rave(policy) => policy.uses_amaf(),
+            _ => false
+        };
+    }
+
+    fn uses_priors(&self) -> bool {
+        return match self {
+            Selection::Puct(policy) => policy.uses_priors(),
+            _ => false
+        };
+    }
+}
+
+
+#[cfg(test)]
+fn node_with_stats(visits: usize, wins: usize, losses: usize) -> Stats {
+    return Stats { visits, wins, losses, draws: visits - wins - losses, ..Stats::default() };
+}
+
+#[test]
+fn test_ucb1_score(){
+    let mut rng = rand::thread_rng();
+    let policy = Ucb1::default();
+
+    assert_eq!(policy.score(&node_with_stats(0, 0, 0), 1, 0., &mut rng), f32::INFINITY);
+    assert_eq!(policy.score(&node_with_stats(1, 1, 0), 1, 0., &mut rng), 1.);
+    assert!((policy.score(&node_with_stats(2, 1, 0), 2, 0., &mut rng) - 2.1651).abs() < 0.0001);
+    assert!((policy.score(&node_with_stats(5, 1, 2), 10, 0., &mut rng) - 1.7194).abs() < 0.0001);
+
+    let policy = Ucb1 { constant: 1. };
+    assert!((policy.score(&node_with_stats(5, 1, 2), 10, 0., &mut rng) - 0.4786).abs() < 0.0001);
+}
+
+#[test]
+fn test_ucb1_tuned_score(){
+    let mut rng = rand::thread_rng();
+    let policy = Ucb1Tuned;
+
+    assert_eq!(policy.score(&node_with_stats(0, 0, 0), 1, 0., &mut rng), f32::INFINITY);
+    // no exploration with a single parent visit
+    assert_eq!(policy.score(&node_with_stats(4, 2, 1), 1, 0., &mut rng), 0.25);
+
+    // variance is capped at 1/4, so the bonus is never larger than UCB1 with constant sqrt(1/4) on [0,1] rewards
+    let tuned = policy.score(&node_with_stats(10, 5, 5), 100, 0., &mut rng);
+    let ucb1 = Ucb1 { constant: 1. }.score(&node_with_stats(10, 5, 5), 100, 0., &mut rng);
+    assert!(tuned <= ucb1 + 0.0001);
+
+    // lower variance means less exploration
+    let all_draws = policy.score(&node_with_stats(1000, 0, 0), 2000, 0., &mut rng);
+    let half_wins = policy.score(&node_with_stats(1000, 500, 500), 2000, 0., &mut rng);
+    assert!(all_draws < half_wins);
+}
+
+#[test]
+fn test_kl_ucb_score(){
+    let mut rng = rand::thread_rng();
+    let policy = KlUcb::default();
+
+    assert_eq!(policy.score(&node_with_stats(0, 0, 0), 1, 0., &mut rng), f32::INFINITY);
+    // the bound is always between the mean and the maximal reward
+    let score = policy.score(&node_with_stats(10, 5, 3), 20, 0., &mut rng);
+    assert!(score > 0.2 && score < 1.);
+
+    // the bound shrinks with more visits
+    let score_more_visits = policy.score(&node_with_stats(100, 50, 30), 200, 0., &mut rng);
+    assert!(score_more_visits > 0.2 && score_more_visits < score);
+}
+
+#[test]
+fn test_thompson_score(){
+    let mut rng = rand::thread_rng();
+    let policy = Thompson;
+
+    for _ in 0..100 {
+        let score = policy.score(&node_with_stats(0, 0, 0), 1, 0., &mut rng);
+        assert!((-1. ..=1.).contains(&score));
+    }
+
+    // a node with many wins almost always beats a node with many losses
+    let good = node_with_stats(100, 90, 5);
+    let bad = node_with_stats(100, 5, 90);
+    let better = (0..100).filter(|_| policy.score(&good, 200, 0., &mut rng) > policy.score(&bad, 200, 0., &mut rng)).count();
+    assert!(better > 95);
+
+    // draw values outside of [-1, 1] would make the parameters of the Beta distribution invalid
+    for draw_value in [-5., 5., f32::NAN] {
+        let score = policy.score(&node_with_stats(10, 2, 3), 20, draw_value, &mut rng);
+        assert!((-1. ..=1.).contains(&score));
+    }
+}
+
+#[test]
+fn test_rave_schedule(){
+    let schedule = RaveSchedule::Equivalence { k: 300. };
+    assert_eq!(schedule.beta(0, 10), 1.);
+    assert!((schedule.beta(300, 1000) - 0.5).abs() < 0.0001);
+    assert!(schedule.beta(1000, 1000) < schedule.beta(300, 1000));
+
+    let schedule = RaveSchedule::MinimumMse { bias: 0. };
+    assert_eq!(schedule.beta(0, 10), 1.);
+    assert!((schedule.beta(10, 30) - 0.75).abs() < 0.0001);
+    assert_eq!(schedule.beta(10, 0), 0.);
+
+    let biased = RaveSchedule::MinimumMse { bias: 0.5 };
+    assert!(biased.beta(10, 30) < schedule.beta(10, 30));
+
+    assert_eq!(RaveSchedule::from_name("equivalence", 300.), Some(RaveSchedule::Equivalence { k: 300. }));
+    assert_eq!(RaveSchedule::from_name("minimum-mse", 0.5), Some(biased));
+    assert_eq!(RaveSchedule::from_name("linear", 0.5), None);
+}
+
+#[test]
+fn test_rave_score(){
+    let mut rng = rand::thread_rng();
+    let policy = Rave { constant: 0., schedule: RaveSchedule::Equivalence { k: 300. } };
+
+    let mut node = node_with_stats(300, 180, 120);
+    assert!((policy.score(&node, 200, 0., &mut rng) - 0.2).abs() < 0.0001);
+
+    // beta is 0.5, so the value is halfway between the node value 0.2 and the all-moves-as-first value -0.6
+    node.amaf_visits = 1000;
+    node.amaf_wins = 200;
+    node.amaf_losses = 800;
+    assert!((policy.score(&node, 200, 0., &mut rng) - -0.2).abs() < 0.0001);
+
+    let policy = Rave::default();
+    assert!(policy.uses_amaf());
+    assert!(! Ucb1::default().uses_amaf());
+    assert!(Selection::from_name("rave").unwrap().uses_amaf());
+}
+
+#[test]
+fn test_puct_score(){
+    let mut rng = rand::thread_rng();
+    let policy = Puct { constant: 1. };
+
+    // unvisited children are scored by their prior
+    let mut stats = node_with_stats(0, 0, 0);
+    stats.prior = 0.5;
+    assert!((policy.score(&stats, 16, 0., &mut rng) - 2.).abs() < 0.0001);
+    stats.prior = 0.25;
+    assert!((policy.score(&stats, 16, 0., &mut rng) - 1.).abs() < 0.0001);
+
+    // value 0.25 and bonus 0.5 * sqrt(16) / 5
+    let mut stats = node_with_stats(4, 2, 1);
+    stats.prior = 0.5;
+    assert!((policy.score(&stats, 16, 0., &mut rng) - 0.65).abs() < 0.0001);
+
+    assert!(policy.uses_priors());
+    assert!(! Ucb1::default().uses_priors());
+    assert!(Selection::from_name("puct").unwrap().uses_priors());
+}
+
+#[test]
+fn test_selection_from_name(){
+    assert_eq!(Selection::from_name("ucb1"), Some(Selection::Ucb1(Ucb1::default())));
+    assert_eq!(Selection::from_name("ucb1-tuned"), Some(Selection::Ucb1Tuned(Ucb1Tuned)));
+    assert_eq!(Selection::from_name("kl-ucb"), Some(Selection::KlUcb(KlUcb { c: 0. })));
+    assert_eq!(Selection::from_name("thompson"), Some(Selection::Thompson(Thompson)));
+    assert_eq!(Selection::from_name("rave"), Some(Selection::Rave(Rave::default())));
+    assert_eq!(Selection::from_name("puct"), Some(Selection::Puct(Puct { constant: 2. })));
+    assert_eq!(Selection::from_name("minimax"), None);
+}
+
+#[test]
+fn test_draw_value(){
+    let mut rng = rand::thread_rng();
+    let drawish = node_with_stats(10, 2, 2);
+    let winning = node_with_stats(10, 4, 3);
+
+    // with a neutral draw value the node with more wins is preferred, with a high draw value the drawish node is preferred
+    let policy = Ucb1 { constant: 0. };
+    assert!(policy.score(&drawish, 20, 0., &mut rng) < policy.score(&winning, 20, 0., &mut rng));
+    assert!(policy.score(&drawish, 20, 0.5, &mut rng) > policy.score(&winning, 20, 0.5, &mut rng));
+
+    let policy = KlUcb::default();
+    assert!(policy.score(&drawish, 20, 0.5, &mut rng) > policy.score(&drawish, 20, -0.5, &mut rng));
+
+    let policy = Ucb1Tuned;
+    assert!(policy.score(&drawish, 20, 0.5, &mut rng) > policy.score(&drawish, 20, -0.5, &mut rng));
+
+    let all_draws = node_with_stats(1000, 0, 0);
+    let policy = Thompson;
+    assert!(policy.score(&all_draws, 2000, 0.9, &mut rng) > 0.5);
+    assert!(policy.score(&all_draws, 2000, -0.9, &mut rng) < -0.5);
+}
@@ -0,0 +1,188 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use rand::Rng;
+
+use super::evaluation::Evaluator;
+use super::mcts::{GeneralGame, SearchConfig};
+use super::move_selection::MoveSelection;
+use super::rollout::RolloutPolicy;
+use super::selection::SelectionPolicy;
+use super::tree::Tree;
+
+/// version of the training-data format written by `DataWriter`
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Settings of games the search plays against itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfPlay {
+    /// iterations of the search before every move, the tree is kept between moves
+    pub iterations: usize,
+    pub rollouts: usize,
+    /// the first `opening_moves` moves are sampled from the visits with `temperature`, later the most visited move is played
+    pub opening_moves: usize,
+    pub temperature: f32
+}
+
+impl Default for SelfPlay {
+    fn default() -> SelfPlay {
+        return SelfPlay { iterations: 1000, rollouts: 1, opening_moves: 8, temperature: 1. };
+    }
+}
+
+/// A position of a self-play game and what the search thought of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position<T: GeneralGame> {
+    pub game: T,
+    /// the player to move
+    pub player: i8,
+    /// moves at the root with their visits
+    pub visits: Vec<(T::Move, usize)>,
+    /// the move which was played
+    pub move_index: T::Move
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord<T: GeneralGame> {
+    pub positions: Vec<Position<T>>,
+    /// the winner, 0 for a draw
+    pub result: i8
+}
+
+impl SelfPlay {
+    /// plays from `game` until the game ends
+    pub fn play<T, S, R, E, G>(&self, game: T, config: &SearchConfig<S, R, E>, rng: &mut G) -> GameRecord<T>
+    where T: GeneralGame, S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized {
+        let root = Tree::<T>::ROOT;
+        let mut tree = Tree::new(game);
+        let mut positions = Vec::new();
+
+        while ! tree.game().get_outcome().is_over() {
+            tree.predict(self.iterations, self.rollouts, config, rng);
+
+            let selection = if positions.len() < self.opening_moves { MoveSelection::Sample { temperature: self.temperature } } else { MoveSelection::MaxVisits };
+            let child = tree.choose_move(&selection, self.rollouts, config, rng).expect("the search did not visit any move");
+
+            let move_index = tree.node(child).move_index;
+            let visits = Vec::from_iter(tree.children(root).map(|id| (tree.node(id).move_index, tree.node(id).stats.visits)));
+            positions.push(Position { game: tree.game().clone(), player: tree.root().player, visits, move_index });
+
+            tree.advance(move_index);
+        }
+
+        return GameRecord { positions, result: tree.game().get_outcome().winner() };
+    }
+}
+
+/// Writes self-play games as text. The first line is "self-play" and `FORMAT_VERSION`, then every position is a line of
+/// tab-separated fields: the index of the game, the ply, the board, the player to move, the visits as "move:visits" separated
+/// by spaces, the move played and the winner of the game. Moves are written with their `Display`, the board is the `Display`
+/// output without whitespace and with rows separated by '/'.
+pub struct DataWriter<W: Write> {
+    writer: W,
+    games: usize
+}
+
+impl<W: Write> DataWriter<W> {
+    /// writes the header
+    pub fn new(mut writer: W) -> io::Result<DataWriter<W>> {
+        writeln!(writer, "self-play {}", FORMAT_VERSION)?;
+        return Ok(DataWriter { writer, games: 0 });
+    }
+
+    pub fn write<T: GeneralGame + Display>(&mut self, record: &GameRecord<T>) -> io::Result<()> {
+        for (ply, position) in record.positions.iter().enumerate() {
+            let board = position.game.to_string().lines().map(|row| row.split_whitespace().collect::<String>()).collect::<Vec<_>>().join("/");
+            let visits = position.visits.iter().map(|(index, visits)| format!("{}:{}", index, visits)).collect::<Vec<_>>().join(" ");
+
+            writeln!(self.writer, "{}\t{}\t{}\t{}\t{}\t{}\t{}", self.games, ply, board, position.player, visits, position.move_index, record.result)?;
+        }
+        self.games += 1;
+
+        return Ok(());
+    }
+
+    /// number of games written
+    pub fn games(&self) -> usize {
+        return self.games;
+    }
+
+    pub fn into_inner(self) -> W {
+        return self.writer;
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::Ucb1;
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+#[test]
+fn test_self_play_game(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let self_play = SelfPlay { iterations: 200, ..Default::default() };
+    let start = TicTacToe::from_string("...\n...\n...").unwrap();
+
+    let record = self_play.play(start.clone(), &config, &mut rng);
+
+    // replaying the moves goes through the recorded positions to the result
+    let mut game = start;
+    for position in record.positions.iter() {
+        assert_eq!(position.game, game);
+        assert_eq!(position.player, GeneralGame::player(&game));
+        assert!(position.visits.iter().any(|&(index, visits)| index == position.move_index && visits > 0));
+        assert_eq!(position.visits.len(), GeneralGame::get_available(&game).len());
+
+        GeneralGame::update(&mut game, position.move_index);
+    }
+    assert!(GeneralGame::get_outcome(&game).is_over());
+    assert_eq!(record.result, GeneralGame::get_outcome(&game).winner());
+}
+
+#[test]
+fn test_self_play_openings(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let game = TicTacToe::from_string("...\n...\n...").unwrap();
+    let is_most_visited = |position: &Position<TicTacToe>| {
+        let played = position.visits.iter().find(|(index, _)| *index == position.move_index).unwrap().1;
+        position.visits.iter().all(|(_, visits)| *visits <= played)
+    };
+
+    // a high temperature plays other moves than the most visited one in the opening
+    let self_play = SelfPlay { iterations: 100, opening_moves: 2, temperature: 100., ..Default::default() };
+    let records = Vec::from_iter((0..10).map(|_| self_play.play(game.clone(), &config, &mut rng)));
+    assert!(records.iter().any(|record| ! record.positions[..2].iter().all(is_most_visited)));
+
+    let self_play = SelfPlay { iterations: 100, opening_moves: 0, ..Default::default() };
+    for _ in 0..10 {
+        assert!(is_most_visited(&self_play.play(game.clone(), &config, &mut rng).positions[0]));
+    }
+}
+
+#[test]
+fn test_data_writer(){
+    let record = GameRecord {
+        positions: vec![
+            Position { game: TicTacToe::from_string("...\n...\n...").unwrap(), player: 1, visits: vec![(Square(0,0), 3), (Square(1,1), 10)], move_index: Square(1,1) },
+            Position { game: TicTacToe::from_string("...\n.X.\n...").unwrap(), player: -1, visits: vec![(Square(0,0), 7)], move_index: Square(0,0) }
+        ],
+        result: 0
+    };
+
+    let mut writer = DataWriter::new(Vec::new()).unwrap();
+    writer.write(&record).unwrap();
+    writer.write(&GameRecord { positions: record.positions[1..].to_vec(), result: -1 }).unwrap();
+    assert_eq!(writer.games(), 2);
+
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), "self-play 1\n\
+        0\t0\t.../.../...\t1\ta1:3 b2:10\tb2\t0\n\
+        0\t1\t.../.X./...\t-1\ta1:7\ta1\t0\n\
+        1\t0\t.../.X./...\t-1\ta1:7\ta1\t-1\n");
+}
@@ -0,0 +1,473 @@
+use super::connect4::Connect4;
+use super::engine::{Budget, Engine};
+use super::mcts::GeneralGame;
+
+/// Position as two bitboards. Every column takes ROWS + 1 bits starting from the bottom, the extra bit on top stays empty
+/// so that lines do not wrap from one column into the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> {
+    /// tokens of the player to move
+    current: u64,
+    /// tokens of both players
+    mask: u64,
+    moves: usize
+}
+
+impl<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> Position<ROWS, COLUMNS, CONNECT> {
+    const CELLS: usize = ROWS * COLUMNS;
+
+    fn bottom() -> u64 {
+        return (0..COLUMNS).fold(0, |bottom, col| bottom | 1 << (col * (ROWS + 1)));
+    }
+
+    fn board() -> u64 {
+        return Self::bottom() * ((1 << ROWS) - 1);
+    }
+
+    fn column(col: usize) -> u64 {
+        return ((1 << ROWS) - 1) << (col * (ROWS + 1));
+    }
+
+    fn new(game: &Connect4<ROWS, COLUMNS, CONNECT>, player: i8) -> Position<ROWS, COLUMNS, CONNECT> {
+        let (mut current, mut mask, mut moves) = (0u64, 0u64, 0usize);
+
+        for col in 0..COLUMNS {
+            for row in 0..ROWS {
+                let cell = game.board[row][col];
+                if cell == 0 {
+                    continue;
+                }
+
+                let bit = 1 << (col * (ROWS + 1) + ROWS - 1 - row);
+                mask |= bit;
+                moves += 1;
+                if cell == player {
+                    current |= bit;
+                }
+            }
+        }
+
+        return Position { current, mask, moves };
+    }
+
+    /// unique for every position
+    fn key(&self) -> u64 {
+        return self.current + self.mask + Self::bottom();
+    }
+
+    /// the lowest empty cell of every column which is not full
+    fn possible(&self) -> u64 {
+        return (self.mask + Self::bottom()) & Self::board();
+    }
+
+    /// the move is a cell of `possible`
+    fn play(&mut self, cell: u64) {
+        self.current ^= self.mask;
+        self.mask |= cell;
+        self.moves += 1;
+    }
+
+    /// empty cells which complete a line of `tokens`
+    fn winning_cells(&self, tokens: u64) -> u64 {
+        let mut winning = 0;
+
+        for direction in [1, ROWS + 1, ROWS, ROWS + 2] {
+            // the empty cell is the `gap`-th cell of the line
+            for gap in 0..CONNECT {
+                let mut line = !0u64;
+                for i in (0..CONNECT).filter(|&i| i != gap) {
+                    line &= if i > gap { tokens >> ((i - gap) * direction) } else { tokens << ((gap - i) * direction) };
+                }
+                winning |= line;
+            }
+        }
+
+        return winning & (Self::board() ^ self.mask);
+    }
+
+    fn can_win_next(&self) -> bool {
+        return self.winning_cells(self.current) & self.possible() != 0;
+    }
+
+    /// moves after which the opponent cannot win right away, assuming that the player to move cannot win right away either
+    fn non_losing_moves(&self) -> u64 {
+        let mut possible = self.possible();
+        let opponent_wins = self.winning_cells(self.current ^ self.mask);
+
+        let forced = possible & opponent_wins;
+        if forced != 0 {
+            // two threats cannot both be blocked
+            if forced & (forced - 1) != 0 {
+                return 0;
+            }
+            possible = forced;
+        }
+
+        // never play right below a winning cell of the opponent
+        return possible & ! (opponent_wins >> 1);
+    }
+
+    /// number of winning cells of the player to move after playing `cell`, used to order the moves
+    fn move_score(&self, cell: u64) -> u32 {
+        return self.winning_cells(self.current | cell).count_ones();
+    }
+}
+
+/// Exact value of a position, from the point of view of the player to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Solution {
+    /// 1 for a win, 0 for a draw, -1 for a loss
+    pub value: i8,
+    /// plies until the game ends with perfect play, the winner wins as fast and the loser loses as slowly as possible
+    pub plies: usize
+}
+
+impl Solution {
+    /// the solution of the position before a move which leads to this one
+    fn before_move(&self) -> Solution {
+        return Solution { value: -self.value, plies: self.plies + 1 };
+    }
+}
+
+/// Perfect solver of Connect4 boards with at most 64 bits of `(ROWS + 1) * COLUMNS`, by negamax on bitboards with alpha-beta
+/// pruning, a transposition table, moves ordered by the threats they create and null-window searches. Scores are positive
+/// for a win of the player to move and larger the sooner the game is won.
+pub struct Solver<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> {
+    /// upper bounds of the score by key, the index is the key modulo the length
+    table: Vec<(u64, i8)>,
+    order: Vec<usize>,
+    nodes: usize
+}
+
+impl<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> Solver<ROWS, COLUMNS, CONNECT> {
+    /// `table_size` is the number of entries of the transposition table, 16 bytes each
+    pub fn new(table_size: usize) -> Solver<ROWS, COLUMNS, CONNECT> {
+        assert!((ROWS + 1) * COLUMNS <= 64, "the board does not fit into 64 bits");
+
+        // central columns first
+        let mut order = Vec::from_iter(0..COLUMNS);
+        order.sort_by_key(|&col| (2 * col).abs_diff(COLUMNS - 1));
+        return Solver { table: vec![(0, 0); table_size.max(1)], order, nodes: 0 };
+    }
+
+    /// nodes visited by the last call of `solve`
+    pub fn nodes(&self) -> usize {
+        return self.nodes;
+    }
+
+    pub fn clear(&mut self) {
+        self.table.fill((0, 0));
+    }
+
+    /// Solves `game` for the player to move. Finished games have 0 plies left, the value of a won game is decided by the
+    /// tokens on the board and not by whose turn it is.
+    pub fn solve(&mut self, game: &Connect4<ROWS, COLUMNS, CONNECT>) -> Solution {
+        self.nodes = 0;
+        let player = game.player();
+
+        let outcome = game.get_outcome();
+        if outcome.is_over() {
+            return Solution { value: outcome.winner() * player, plies: 0 };
+        }
+
+        let position = Position::new(game, player);
+        let score = self.solve_score(&position);
+        return Solver::<ROWS, COLUMNS, CONNECT>::solution(score, position.moves);
+    }
+
+    /// Solutions after each move of the player to move, indexed by column and `None` for full columns. Every solution is from
+    /// the point of view of that player and includes the move, so the best moves are the ones with the best solution.
+    pub fn analyze(&mut self, game: &Connect4<ROWS, COLUMNS, CONNECT>) -> Vec<Option<Solution>> {
+        let mut nodes = 0;
+        let solutions = Vec::from_iter((0..COLUMNS).map(|col| {
+            if game.board[0][col] != 0 || game.get_outcome().is_over() {
+                return None;
+            }
+
+            let mut child = game.clone();
+            child.update(col);
+            let solution = self.solve(&child).before_move();
+            nodes += self.nodes;
+            Some(solution)
+        }));
+        self.nodes = nodes;
+
+        return solutions;
+    }
+
+    /// a column with the best solution for the player to move, the first one from the center if several are equally good
+    pub fn best_move(&mut self, game: &Connect4<ROWS, COLUMNS, CONNECT>) -> Option<usize> {
+        let solutions = self.analyze(game);
+        let rank = |solution: Solution| (solution.value, if solution.value > 0 { -(solution.plies as i64) } else { solution.plies as i64 });
+
+        let mut best: Option<(usize, Solution)> = None;
+        for &col in self.order.iter() {
+            if let Some(solution) = solutions[col] {
+                if best.is_none_or(|(_, best)| rank(solution) > rank(best)) {
+                    best = Some((col, solution));
+                }
+            }
+        }
+
+        return best.map(|(col, _)| col);
+    }
+
+    /// turns a score into the value and the plies to the end, see `negamax` for the scores
+    fn solution(score: i32, moves: usize) -> Solution {
+        let remaining = (Position::<ROWS, COLUMNS, CONNECT>::CELLS - moves) as i32;
+
+        return match score.signum() {
+            // the winner plays its k-th token from now, where the score is (remaining + 3) / 2 - k
+            1 => Solution { value: 1, plies: (2 * ((remaining + 3) / 2 - score) - 1) as usize },
+            -1 => Solution { value: -1, plies: (2 * ((remaining + 2) / 2 + score)) as usize },
+            _ => Solution { value: 0, plies: remaining as usize }
+        };
+    }
+
+    /// exact score by null-window searches which bisect the range of possible scores
+    fn solve_score(&mut self, position: &Position<ROWS, COLUMNS, CONNECT>) -> i32 {
+        let cells = Position::<ROWS, COLUMNS, CONNECT>::CELLS as i32;
+        let moves = position.moves as i32;
+
+        if moves == cells {
+            return 0;
+        }
+        if position.can_win_next() {
+            return (cells + 1 - moves) / 2;
+        }
+
+        let (mut min, mut max) = (-(cells - moves) / 2, (cells + 1 - moves) / 2);
+        while min < max {
+            // trying values close to 0 first finds the sign quickly
+            let mut middle = min + (max - min) / 2;
+            if middle <= 0 && min / 2 < middle {
+                middle = min / 2;
+            }
+            else if middle >= 0 && max / 2 > middle {
+                middle = max / 2;
+            }
+
+            let score = self.negamax(position, middle, middle + 1);
+            if score <= middle {
+                max = score;
+            }
+            else {
+                min = score;
+            }
+        }
+
+        return min;
+    }
+
+    /// Score of a position where the player to move cannot win right away. A win with the k-th token of the winner counted
+    /// from the start of the game is worth `(CELLS + 1) / 2 + 1 - k`, a loss the negative of that and a draw 0. Returns
+    /// a value at most `alpha` if the score is at most `alpha`, at least `beta` if it is at least `beta` and the score otherwise.
+    fn negamax(&mut self, position: &Position<ROWS, COLUMNS, CONNECT>, mut alpha: i32, mut beta: i32) -> i32 {
+        self.nodes += 1;
+        let cells = Position::<ROWS, COLUMNS, CONNECT>::CELLS as i32;
+        let moves = position.moves as i32;
+
+        let next = position.non_losing_moves();
+        if next == 0 {
+            return -(cells - moves) / 2;
+        }
+        if moves >= cells - 2 {
+            return 0;
+        }
+
+        // the opponent cannot win with its next token
+        let min = -(cells - 2 - moves) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        // the player to move cannot win with its next token either
+        let key = position.key();
+        let index = (key % self.table.len() as u64) as usize;
+        let max = match self.table[index] {
+            (stored, bound) if stored == key => bound as i32,
+            _ => (cells - 1 - moves) / 2
+        };
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        let mut children = Vec::with_capacity(COLUMNS);
+        for &col in self.order.iter() {
+            let cell = next & Position::<ROWS, COLUMNS, CONNECT>::column(col);
+            if cell != 0 {
+                children.push((position.move_score(cell), cell));
+            }
+        }
+        // stable, so equal scores keep the central columns first
+        children.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+
+        for (_, cell) in children {
+            let mut child = *position;
+            child.play(cell);
+
+            let score = -self.negamax(&child, -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+
+        self.table[index] = (key, alpha as i8);
+        return alpha;
+    }
+}
+
+/// plays the best move regardless of the budget
+impl<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize> Engine<Connect4<ROWS, COLUMNS, CONNECT>> for Solver<ROWS, COLUMNS, CONNECT> {
+    fn choose_move(&mut self, game: &Connect4<ROWS, COLUMNS, CONNECT>, _budget: Budget) -> Option<usize> {
+        return self.best_move(game);
+    }
+}
+
+
+#[cfg(test)]
+use rand::{Rng, SeedableRng, rngs::StdRng};
+#[cfg(test)]
+use super::mcts::Outcome;
+#[cfg(test)]
+use std::collections::HashMap;
+
+/// plain minimax over every line of play, to check the solver on small boards
+#[cfg(test)]
+fn brute_force<const ROWS: usize, const COLUMNS: usize, const CONNECT: usize>(game: &Connect4<ROWS, COLUMNS, CONNECT>, player: i8, cache: &mut HashMap<(Connect4<ROWS, COLUMNS, CONNECT>, i8), Solution>) -> Solution {
+    let outcome = game.get_outcome();
+    if outcome.is_over() {
+        return Solution { value: outcome.winner() * player, plies: 0 };
+    }
+    if let Some(&solution) = cache.get(&(game.clone(), player)) {
+        return solution;
+    }
+
+    let mut best: Option<Solution> = None;
+    for col in game.get_available() {
+        let mut child = game.clone();
+        child.update(col);
+        let solution = brute_force(&child, -player, cache).before_move();
+
+        let better = match best {
+            None => true,
+            Some(best) if solution.value != best.value => solution.value > best.value,
+            Some(best) if solution.value > 0 => solution.plies < best.plies,
+            Some(best) => solution.plies > best.plies
+        };
+        if better {
+            best = Some(solution);
+        }
+    }
+
+    let solution = best.unwrap_or(Solution { value: 0, plies: 0 });
+    cache.insert((game.clone(), player), solution);
+    return solution;
+}
+
+#[test]
+fn test_solver_position(){
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ...O...\n\
+                        ..OX...\n\
+                        .OXX.X.\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+    let position = Position::new(&game, 1);
+
+    assert_eq!(position.moves, 7);
+    assert_eq!(position.possible().count_ones(), 7);
+    // X completes the bottom row in column 4, O the diagonal in the fourth row of column 4
+    assert_eq!(position.winning_cells(position.current), 1 << (4 * 7));
+    assert_eq!(position.winning_cells(position.current ^ position.mask), 1 << (4 * 7 + 3));
+    assert!(position.can_win_next());
+    assert_eq!(position.key(), Position::new(&game, 1).key());
+    assert_ne!(position.key(), Position::new(&game, -1).key());
+}
+
+#[test]
+fn test_solver_simple(){
+    let mut solver = Solver::<6,7,4>::new(1 << 16);
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        O......\n\
+                        O..XXX.\n\
+                    ";
+    let game = Connect4::<6,7,4>::from_string(str).unwrap();
+
+    // O can block only one side
+    assert_eq!(solver.solve(&game), Solution { value: -1, plies: 2 });
+    let analysis = solver.analyze(&game);
+    assert_eq!(analysis[2], Some(Solution { value: -1, plies: 2 }));
+    assert_eq!(analysis[0], Some(Solution { value: -1, plies: 2 }));
+
+    // X wins right away
+    let game = Connect4::<6,7,4>::from_string(&str.replace("O......", "O..O...")).unwrap();
+    assert_eq!(solver.solve(&game), Solution { value: 1, plies: 1 });
+    assert_eq!(solver.best_move(&game), Some(2));
+
+    // X makes a row of three open on both sides with column 2
+    let game = Connect4::<6,7,4>::from_string(&str.replace("O......", "O.....X").replace("O..XXX.", "O..XX.O")).unwrap();
+    assert_eq!(solver.solve(&game), Solution { value: 1, plies: 3 });
+
+    // a finished game
+    let won = Connect4::<6,7,4>::from_string(&str.replace("O......", "O..O...").replace("O..XXX.", "O..XXXX")).unwrap();
+    assert_eq!(solver.solve(&won), Solution { value: -1, plies: 0 });
+    assert_eq!(solver.analyze(&won), vec![None; 7]);
+}
+
+#[test]
+fn test_solver_brute_force(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut solver = Solver::<4,5,4>::new(1 << 16);
+    let mut cache = HashMap::new();
+
+    // random positions which are not over yet
+    for _ in 0..20 {
+        let mut game = Connect4::<4,5,4>::empty();
+        let plies = rng.gen_range(6..14);
+        for _ in 0..plies {
+            let available = game.get_available();
+            let col = available[rng.gen_range(0..available.len())];
+            game.update(col);
+            if game.get_outcome().is_over() {
+                break;
+            }
+        }
+
+        let player = game.player();
+        assert_eq!(solver.solve(&game), brute_force(&game, player, &mut cache), "{}", game);
+    }
+}
+
+#[test]
+fn test_solver_small_boards(){
+    // connect 3 on four columns is a win for the first player, connect 4 on a 4x4 board a draw
+    let solution = Solver::<3,4,3>::new(1 << 12).solve(&Connect4::empty());
+    assert_eq!(solution, brute_force(&Connect4::<3,4,3>::empty(), 1, &mut HashMap::new()));
+    assert_eq!(solution.value, 1);
+
+    let solution = Solver::<4,4,4>::new(1 << 16).solve(&Connect4::empty());
+    assert_eq!(solution, Solution { value: 0, plies: 16 });
+}
+
+#[test]
+fn test_solver_engine(){
+    let mut solver = Solver::<4,4,4>::new(1 << 16);
+    let mut opponent = Solver::<4,4,4>::new(1 << 16);
+
+    assert_eq!(super::engine::play(Connect4::<4,4,4>::empty(), &mut solver, &mut opponent, Budget::Nodes(0)), Outcome::Draw);
+}
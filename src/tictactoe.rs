@@ -1,13 +1,69 @@
 use std::fmt;
-use super::mcts::GeneralGame;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use super::mcts::{GeneralGame, Outcome};
 
-#[derive(Debug, Clone, PartialEq)]
+/// row and column of a cell, written as the column letter and the row number counted from the top, e.g. "c1" for (0, 2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Square(pub usize, pub usize);
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.1 as u8) as char, self.0 + 1)
+    }
+}
+
+impl FromStr for Square {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Square, String> {
+        let chars: Vec<char> = val.chars().collect();
+        let [col @ 'a'..='c', row @ '1'..='3'] = chars[..] else {
+            return Err(format!("invalid square {}", val));
+        };
+
+        return Ok(Square(row as usize - '1' as usize, col as usize - 'a' as usize));
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TicTacToe{
-    pub board: [[i8; 3]; 3]
+    pub board: [[i8; 3]; 3],
+    player: i8,
+    history: Vec<Square>
+}
+
+/// positions are equal regardless of the moves which led to them
+impl PartialEq for TicTacToe {
+    fn eq(&self, other: &Self) -> bool {
+        return self.board == other.board && self.player == other.player;
+    }
+}
+
+impl Eq for TicTacToe {}
+
+impl Hash for TicTacToe {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.player.hash(state);
+    }
 }
 
 impl TicTacToe {
-    pub fn get_score(&self) -> i8 {
+    /// `None` if the numbers of tokens cannot be reached by taking turns, X starts so O is to move if X has more tokens
+    pub fn from_board(board: [[i8; 3]; 3]) -> Option<TicTacToe> {
+        let tokens: i8 = board.iter().flatten().sum();
+        let player = match tokens {
+            0 => 1,
+            1 => -1,
+            _ => return None
+        };
+
+        return Some(TicTacToe {board, player, history: Vec::new()});
+    }
+
+    /// 1 or -1 if the player has three tokens in a line, 0 otherwise
+    fn winner(&self) -> i8 {
         for target in [-1i8, 1i8]{
             // check rows and columns
             for i in 0..3usize{
@@ -31,12 +87,12 @@ impl TicTacToe {
         return 0;
     }
 
-    pub fn get_available(&self) -> Vec<(usize, usize)> {
-        let mut res : Vec<(usize, usize)> = Vec::new();
+    pub fn get_available(&self) -> Vec<Square> {
+        let mut res : Vec<Square> = Vec::new();
         for i in 0..3usize{
             for j in 0..3usize{
                 if self.board[i][j] == 0{
-                    res.push((i,j));
+                    res.push(Square(i,j));
                 }
             }
         }
@@ -45,7 +101,7 @@ impl TicTacToe {
     }
 
     pub fn from_string(val : &str) -> Option<TicTacToe> {
-        let mut tictactoe = TicTacToe {board:[[0;3];3]};
+        let mut board = [[0;3];3];
 
         for (i,s) in val.chars().enumerate(){
             if (i+1)%4==0 {
@@ -62,29 +118,66 @@ impl TicTacToe {
                     };
                     let x = i%4;
                     let y = i/4;
-                    tictactoe.board[y][x] = target;
+                    board[y][x] = target;
                 }
             }
         }
 
-        return Some(tictactoe)
+        return TicTacToe::from_board(board);
     }
 
-    pub fn update(&mut self, indeces : (usize, usize), player: i8) {
-        self.board[indeces.0][indeces.1] = player;
+    pub fn update(&mut self, square : Square) {
+        self.board[square.0][square.1] = self.player;
+        self.player = -self.player;
+        self.history.push(square);
     }
 }
 
 impl GeneralGame for TicTacToe {
-    fn get_score(&self) -> i8 {
-        return self.get_score();
+    type Move = Square;
+
+    fn get_outcome(&self) -> Outcome {
+        let winner = self.winner();
+        if winner != 0 {
+            return Outcome::Win(winner);
+        }
+        if self.board.iter().flatten().all(|&cell| cell != 0) {
+            return Outcome::Draw;
+        }
+
+        return Outcome::Ongoing;
+    }
+    fn update(&mut self, square: Square) {
+        self.update(square);
     }
-    fn update(&mut self, index:usize, player:i8) {
-        self.update((index/3, index%3), player);
+
+    fn undo(&mut self) -> Option<Square> {
+        let square = self.history.pop()?;
+        self.board[square.0][square.1] = 0;
+        self.player = -self.player;
+        return Some(square);
+    }
+
+    fn last_move(&self) -> Option<Square> {
+        return self.history.last().copied();
+    }
+
+    fn player(&self) -> i8 {
+        return self.player;
     }
 
-    fn get_available(&self) -> Vec<usize> {
-        return Vec::from_iter(self.get_available().iter().map(|(i,j)| i*3+j));
+    fn winning_moves(&self, player: i8) -> Vec<Square> {
+        let mut game = self.clone();
+        return Vec::from_iter(self.get_available().into_iter().filter(|&square| {
+            game.board[square.0][square.1] = player;
+            let won = game.winner() == player;
+            game.board[square.0][square.1] = 0;
+            won
+        }));
+    }
+
+    fn get_available(&self) -> Vec<Square> {
+        return self.get_available();
     }
 }
 
@@ -94,7 +187,7 @@ impl fmt::Display for TicTacToe {
             for j in 0..3usize{
                 write!(f, "{} ", if self.board[i][j] == 1 {'X'} else if self.board[i][j] == -1 {'O'} else {'.'}).unwrap();
             }
-            write!(f, "\n").unwrap();
+            writeln!(f).unwrap();
         }
         write!(f, "")
     }
@@ -104,39 +197,60 @@ impl fmt::Display for TicTacToe {
 fn test_tictactoe_score() {
     let mut tictactoe: TicTacToe;
 
-    tictactoe = TicTacToe {board: [[0,0,0],[0,0,0],[0,0,0]]};
-    assert_eq!(tictactoe.get_score(), 0);
+    tictactoe = TicTacToe::from_board([[0,0,0],[0,0,0],[0,0,0]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Ongoing);
+
+    tictactoe = TicTacToe::from_board([[1,-1,0],[1,-1,0],[1,0,0]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Win(1));
 
-    tictactoe = TicTacToe {board: [[1,0,0],[1,0,0],[1,0,0]]};
-    assert_eq!(tictactoe.get_score(), 1);
+    tictactoe = TicTacToe::from_board([[1,-1,0],[0,-1,1],[1,-1,0]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Win(-1));
 
-    tictactoe = TicTacToe {board: [[0,-1,0],[0,-1,0],[0,-1,0]]};
-    assert_eq!(tictactoe.get_score(), -1);
+    tictactoe = TicTacToe::from_board([[-1,1,0],[0,-1,1],[1,0,-1]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Win(-1));
 
-    tictactoe = TicTacToe {board: [[-1,0,0],[0,-1,0],[0,0,-1]]};
-    assert_eq!(tictactoe.get_score(), -1);
+    tictactoe = TicTacToe::from_board([[-1,0,1],[0,1,0],[1,0,-1]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Win(1));
 
-    tictactoe = TicTacToe {board: [[-1,0,1],[0,1,0],[1,0,-1]]};
-    assert_eq!(tictactoe.get_score(), 1);
+    tictactoe = TicTacToe::from_board([[-1,-1,1],[0,-1,1],[1,0,1]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Win(1));
 
-    tictactoe = TicTacToe {board: [[-1,0,1],[0,0,1],[1,0,1]]};
-    assert_eq!(tictactoe.get_score(), 1);
+    tictactoe = TicTacToe::from_board([[1,-1,1],[1,-1,-1],[-1,1,1]]).unwrap();
+    assert_eq!(tictactoe.get_outcome(), Outcome::Draw);
 }
 
 #[test]
 fn test_tictactoe_available() {
     let mut tictactoe: TicTacToe;
 
-    tictactoe = TicTacToe {board: [[0,0,0],[0,-1,0],[1,0,-1]]};
-    assert_eq!(tictactoe.get_available(), [(0,0),(0,1),(0,2),(1,0),(1,2),(2,1)]);
+    tictactoe = TicTacToe::from_board([[0,0,0],[0,-1,0],[1,0,1]]).unwrap();
+    assert_eq!(tictactoe.get_available(), [Square(0,0),Square(0,1),Square(0,2),Square(1,0),Square(1,2),Square(2,1)]);
 
-    tictactoe = TicTacToe {board: [[-1,1,0],[-1,-1,-1],[1,1,0]]};
-    assert_eq!(tictactoe.get_available(), [(0,2),(2,2)]);
+    tictactoe = TicTacToe::from_board([[-1,1,0],[1,-1,-1],[1,1,0]]).unwrap();
+    assert_eq!(tictactoe.get_available(), [Square(0,2),Square(2,2)]);
+}
+
+#[test]
+fn test_tictactoe_moves() {
+    assert_eq!(Square(0,2).to_string(), "c1");
+    assert_eq!("b3".parse(), Ok(Square(2,1)));
+    assert!("d1".parse::<Square>().is_err());
+    assert!("a".parse::<Square>().is_err());
+
+    let mut tictactoe = TicTacToe::from_board([[1,0,0],[0,-1,0],[0,0,0]]).unwrap();
+    assert_eq!(tictactoe.parse_move(" b1 "), Some(Square(0,1)));
+    assert_eq!(tictactoe.parse_move("a1"), None);
+    assert_eq!(tictactoe.parse_move("1"), None);
+
+    GeneralGame::update(&mut tictactoe, Square(2,2));
+    assert_eq!(tictactoe.last_move(), Some(Square(2,2)));
+    assert_eq!(tictactoe.undo(), Some(Square(2,2)));
+    assert_eq!(tictactoe.board[2][2], 0);
 }
 
 #[test]
 fn test_tictactoe_fmt(){
-    let tictactoe = TicTacToe {board: [[1,1,-1],[0,0,-1],[1,0,0]]};
+    let tictactoe = TicTacToe::from_board([[1,1,-1],[0,0,-1],[1,0,0]]).unwrap();
     let tictactoe_str = format!("{}", tictactoe);
     assert_eq!(tictactoe_str, "X X O \n. . O \nX . . \n");
 }
@@ -146,8 +260,12 @@ fn test_tictactoe_from_string(){
     let mut tictactoe;
 
     tictactoe = TicTacToe::from_string("XX.\nO.O\n..X\r");
-    assert_eq!(tictactoe, Some(TicTacToe {board: [[1,1,0],[-1,0,-1],[0,0,1]]}));
+    assert_eq!(tictactoe, TicTacToe::from_board([[1,1,0],[-1,0,-1],[0,0,1]]));
 
     tictactoe = TicTacToe::from_string("XX.\rO.O\n...X\n");
     assert_eq!(tictactoe, None);
+
+    // O can not have more tokens than X, nor X two more than O
+    assert_eq!(TicTacToe::from_string("O..\n...\n..."), None);
+    assert_eq!(TicTacToe::from_board([[1,1,0],[0,0,0],[0,0,0]]), None);
 }
\ No newline at end of file
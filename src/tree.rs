@@ -0,0 +1,775 @@
+use std::ops::Range;
+
+use rand::Rng;
+
+use super::clock::Clock;
+use super::evaluation::{evaluate_expansion, simulate, Evaluator};
+use super::move_selection::MoveSelection;
+use super::mcts::{backup_proven, most_visited_child, select_child, terminal_value, GeneralGame, Proven, SearchConfig, Stats};
+use super::rollout::RolloutPolicy;
+use super::selection::SelectionPolicy;
+
+/// Index of a node in the arena of a `Tree`
+pub type NodeId = usize;
+
+/// Node of a `Tree` with moves of type `M`. The position is not stored, it is replayed from the root of the tree while descending.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TreeNode<M> {
+    /// player to move
+    pub player: i8,
+    pub move_index: M,
+    pub stats: Stats,
+    pub proven: Proven,
+    /// the children are stored next to each other in the arena, starting at `first_child`
+    first_child: u32,
+    child_count: u32,
+    expanded: bool
+}
+
+impl<M> TreeNode<M> {
+    fn new(player: i8, move_index: M) -> TreeNode<M> {
+        return TreeNode { player, move_index, stats: Stats::default(), proven: Proven::Unknown, first_child: 0, child_count: 0, expanded: false };
+    }
+
+    /// average reward of the node, where a draw is worth `draw_value`
+    pub fn value(&self, draw_value: f32) -> f32 {
+        return self.stats.value(draw_value);
+    }
+
+    fn children(&self) -> Range<NodeId> {
+        let first = self.first_child as NodeId;
+        return first..first + self.child_count as NodeId;
+    }
+}
+
+/// Maximum number of nodes of a `Tree` and what happens once it is reached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeLimit {
+    /// leaves are not expanded any more, but are still evaluated by rollouts
+    StopExpansion(usize),
+    /// the subtrees of the least visited nodes are dropped until the tree holds at most half of the nodes
+    Prune(usize)
+}
+
+impl NodeLimit {
+    pub fn max_nodes(&self) -> usize {
+        return match self {
+            NodeLimit::StopExpansion(max_nodes) | NodeLimit::Prune(max_nodes) => *max_nodes
+        };
+    }
+}
+
+/// Search tree stored in a flat arena of nodes, the root is `Tree::ROOT`. Selection and backup are iterative,
+/// and only the position at the root is stored, so the tree is compact and is reclaimed in one piece.
+/// A node of Connect4 takes 96 bytes instead of the 192 of `Node`, but the search is only about 10-50% faster,
+/// since most of the time of an iteration goes into the rollout (see `test_tree_speed`).
+#[derive(Debug, Clone)]
+pub struct Tree<T: GeneralGame> {
+    game: T,
+    nodes: Vec<TreeNode<T::Move>>,
+    node_limit: Option<NodeLimit>,
+    /// buffers reused between the iterations
+    path: Vec<NodeId>,
+    moves: Vec<(T::Move, i8)>
+}
+
+impl<T: GeneralGame + PartialEq> PartialEq for Tree<T> {
+    fn eq(&self, other: &Tree<T>) -> bool {
+        return self.game == other.game && self.nodes == other.nodes;
+    }
+}
+
+impl<T: GeneralGame> Tree<T> {
+    pub const ROOT: NodeId = 0;
+
+    /// tree with only the root
+    pub fn new(game: T) -> Tree<T> {
+        return Tree { nodes: vec![TreeNode::new(game.player(), T::Move::default())], game, node_limit: None, path: Vec::new(), moves: Vec::new() };
+    }
+
+    /// position at the root
+    pub fn game(&self) -> &T {
+        return &self.game;
+    }
+
+    pub fn root(&self) -> &TreeNode<T::Move> {
+        return &self.nodes[Tree::<T>::ROOT];
+    }
+
+    pub fn node(&self, id: NodeId) -> &TreeNode<T::Move> {
+        return &self.nodes[id];
+    }
+
+    /// ids of the children of `id`, empty if the node was not expanded yet
+    pub fn children(&self, id: NodeId) -> Range<NodeId> {
+        return self.nodes[id].children();
+    }
+
+    /// number of nodes in the tree, including children which were created but not visited yet
+    pub fn node_count(&self) -> usize {
+        return self.nodes.len();
+    }
+
+    /// bytes allocated by the tree
+    pub fn memory_usage(&self) -> usize {
+        return std::mem::size_of::<Tree<T>>()
+            + self.nodes.capacity() * std::mem::size_of::<TreeNode<T::Move>>()
+            + self.path.capacity() * std::mem::size_of::<NodeId>()
+            + self.moves.capacity() * std::mem::size_of::<(T::Move, i8)>();
+    }
+
+    pub fn node_limit(&self) -> Option<NodeLimit> {
+        return self.node_limit;
+    }
+
+    /// limits the number of nodes of the tree, `None` lets it grow without bound. A tree which is already
+    /// larger is pruned right away if the limit prunes, otherwise it just stops growing.
+    pub fn set_node_limit(&mut self, node_limit: Option<NodeLimit>) {
+        self.node_limit = node_limit;
+
+        if let Some(NodeLimit::Prune(max_nodes)) = node_limit {
+            if self.nodes.len() > max_nodes {
+                self.prune(max_nodes / 2);
+            }
+        }
+    }
+
+    /// moves which create the children of a position, none if the game is over
+    fn child_moves(game: &T) -> Vec<T::Move> {
+        // If the game is over, there is no point in creating children
+        if game.get_outcome().is_over() {
+            return Vec::new();
+        }
+
+        return game.get_available();
+    }
+
+    /// returns whether `children` more nodes fit into the node limit, reserving the memory for them
+    fn make_room(&mut self, children: usize) -> bool {
+        let needed = self.nodes.len() + children;
+
+        if let Some(node_limit) = self.node_limit {
+            let max_nodes = node_limit.max_nodes();
+            if needed > max_nodes {
+                return false;
+            }
+
+            // grow like a Vec would, but never beyond the limit
+            if needed > self.nodes.capacity() {
+                let capacity = (2 * self.nodes.capacity()).max(needed).min(max_nodes);
+                self.nodes.reserve_exact(capacity - self.nodes.len());
+            }
+        }
+
+        return true;
+    }
+
+    /// appends the children reached by `moves` to the arena, with uniform priors
+    fn expand(&mut self, id: NodeId, moves: Vec<T::Move>) {
+        let nodes = &mut self.nodes;
+        let first_child = nodes.len();
+        let player = nodes[id].player;
+        let prior = 1. / moves.len() as f32;
+
+        nodes.extend(moves.into_iter().map(|index| {
+            let mut child = TreeNode::new(-player, index);
+            child.stats.prior = prior;
+            child
+        }));
+
+        let child_count = nodes.len() - first_child;
+        let node = &mut nodes[id];
+        node.first_child = first_child as u32;
+        node.child_count = child_count as u32;
+        node.expanded = true;
+    }
+
+    /// One iteration of the search, same as `Node::propagate`. Returns (player1 wins, player-1 wins, draws)
+    pub fn propagate<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&mut self, rollouts: usize, config: &SearchConfig<S, R, E>, rng: &mut G) -> (usize, usize, usize) {
+        let amaf = config.selection.uses_amaf();
+        let mut prune = false;
+        let mut id = Tree::<T>::ROOT;
+        let root_player = self.nodes[id].player;
+        self.path.clear();
+        self.moves.clear();
+
+        // descend until a proven or a new node, whether the leaf collects all-moves-as-first statistics is returned as well.
+        // The moves are played on the position at the root and taken back after the descent.
+        let ((wins_1, wins_n1, draws), leaf_amaf) = loop {
+            self.path.push(id);
+            let node = &mut self.nodes[id];
+            node.stats.visits += rollouts;
+
+            if node.proven == Proven::Unknown && ! node.expanded {
+                node.proven = terminal_value(&self.game, node.player);
+            }
+
+            // if the result is already known, just return it
+            let winner = match node.proven {
+                Proven::Win => -node.player,
+                Proven::Loss => node.player,
+                Proven::Draw => break ((0, 0, rollouts), false),
+                Proven::Unknown => 0
+            };
+            if winner == 1 {
+                break ((rollouts, 0, 0), false);
+            }
+            else if winner == -1 {
+                break ((0, rollouts, 0), false);
+            }
+
+            // If the children have not been created yet, do rollouts and initialize children if they fit into the node limit
+            let player = node.player;
+            if ! node.expanded {
+                let moves = Tree::child_moves(&self.game);
+                let mut leaf_value = None;
+                if self.make_room(moves.len()) {
+                    self.expand(id, moves);
+                    if let Some((priors, value)) = evaluate_expansion(&self.game, player, config) {
+                        let children = self.children(id);
+                        for (child, prior) in self.nodes[children].iter_mut().zip(priors.into_iter().flatten()) {
+                            child.stats.prior = prior;
+                        }
+                        leaf_value = Some(value);
+                    }
+                }
+                else {
+                    prune = matches!(self.node_limit, Some(NodeLimit::Prune(_)));
+                }
+
+                let mut results = (0usize, 0usize, 0usize);
+                for _ in 0..rollouts {
+                    let moves = if amaf { Some(&mut self.moves) } else { None };
+                    match simulate(&mut self.game, player, config, rng, moves, leaf_value) {
+                        1 => results.0 += 1,
+                        -1 => results.1 += 1,
+                        _ => results.2 += 1
+                    }
+                }
+                break (results, amaf);
+            }
+
+            let children = self.children(id);
+            let visits = self.nodes[id].stats.visits;
+            // the opponent of the player at the root values a draw the other way round
+            let draw_value = if player == root_player { config.clamped_draw_value() } else { -config.clamped_draw_value() };
+            let next = select_child(self.nodes[children.clone()].iter().map(|child| (child.stats, child.proven)), visits, &config.selection, draw_value, rng);
+            match next {
+                Some(offset) => {
+                    id = children.start + offset;
+                    self.game.update(self.nodes[id].move_index);
+                },
+                // every child is a proven loss but the node is not proven yet, the visit counts as a draw to keep the statistics balanced
+                None => {
+                    self.update_proven(id);
+                    break ((0, 0, rollouts), amaf);
+                }
+            }
+        };
+
+        for _ in 1..self.path.len() {
+            self.game.undo();
+        }
+
+        // back up from the leaf to the root
+        for depth in (0..self.path.len()).rev() {
+            let id = self.path[depth];
+
+            if let Some(&child) = self.path.get(depth + 1) {
+                if amaf {
+                    self.moves.push((self.nodes[child].move_index, self.nodes[id].player));
+                }
+                self.update_proven(id);
+            }
+
+            let node = &mut self.nodes[id];
+            if node.player == -1 {
+                node.stats.wins += wins_1;
+                node.stats.losses += wins_n1;
+            }
+            else if node.player == 1 {
+                node.stats.wins += wins_n1;
+                node.stats.losses += wins_1;
+            }
+            node.stats.draws += draws;
+
+            if amaf && (depth + 1 < self.path.len() || leaf_amaf) {
+                self.update_amaf(id, wins_1, wins_n1, draws);
+            }
+        }
+
+        if prune {
+            self.prune(self.node_limit.unwrap().max_nodes() / 2);
+        }
+
+        return (wins_1, wins_n1, draws);
+    }
+
+    /// Drops the subtrees of the least visited nodes until at most `target` nodes are left, the root and its children are always kept.
+    /// The dropped nodes keep their statistics and are expanded again when they are visited.
+    fn prune(&mut self, target: usize) {
+        let root = Tree::<T>::ROOT;
+        let mut expanded: Vec<(usize, NodeId)> = (1..self.nodes.len())
+            .filter(|&id| self.nodes[id].child_count > 0)
+            .map(|id| (self.nodes[id].stats.visits, id))
+            .collect();
+        expanded.sort_unstable_by_key(|&(visits, _)| std::cmp::Reverse(visits));
+
+        // a child is visited at most as often as its parent, so keeping the nodes with more than `threshold` visits keeps whole subtrees
+        let mut kept = 1 + self.nodes[root].child_count as usize;
+        let mut threshold = None;
+        for &(visits, id) in expanded.iter() {
+            kept += self.nodes[id].child_count as usize;
+            if kept > target {
+                threshold = Some(visits);
+                break;
+            }
+        }
+
+        if let Some(threshold) = threshold {
+            for node in self.nodes[1..].iter_mut() {
+                if node.stats.visits <= threshold {
+                    node.expanded = false;
+                    node.child_count = 0;
+                }
+            }
+            self.retain(root);
+        }
+    }
+
+    /// updates the all-moves-as-first statistics of every child whose move was played by the player of `id` later in the simulation
+    fn update_amaf(&mut self, id: NodeId, wins_1: usize, wins_n1: usize, draws: usize) {
+        let player = self.nodes[id].player;
+        let (wins, losses) = if player == 1 { (wins_1, wins_n1) } else { (wins_n1, wins_1) };
+
+        let children = self.children(id);
+        for child in self.nodes[children].iter_mut() {
+            if self.moves.contains(&(child.move_index, player)) {
+                child.stats.amaf_visits += wins + losses + draws;
+                child.stats.amaf_wins += wins;
+                child.stats.amaf_losses += losses;
+                child.stats.amaf_draws += draws;
+            }
+        }
+    }
+
+    /// backs up the proven values of the children of `id`
+    fn update_proven(&mut self, id: NodeId) {
+        let proven = backup_proven(self.nodes[self.children(id)].iter().map(|child| child.proven));
+        if proven != Proven::Unknown {
+            self.nodes[id].proven = proven;
+        }
+    }
+
+    /// same as `Node::get_most_visited_child`, for the children of `id`
+    pub fn get_most_visited_child(&self, id: NodeId) -> Option<NodeId> {
+        let children = self.children(id);
+        return most_visited_child(self.nodes[children.clone()].iter().map(|child| (child.stats, child.proven))).map(|offset| children.start + offset);
+    }
+
+    /// Picks the move to play at the root with `selection`. For `MoveSelection::RobustMax` the search is extended one iteration
+    /// at a time until the most visited move also has the highest value, or the extension is used up.
+    pub fn choose_move<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&mut self, selection: &MoveSelection, rollouts: usize, config: &SearchConfig<S, R, E>, rng: &mut G) -> Option<NodeId> {
+        let root = Tree::<T>::ROOT;
+
+        if let MoveSelection::RobustMax { max_extension } = selection {
+            for _ in 0..*max_extension {
+                let children = self.children(root);
+                let agree = selection.agrees(self.nodes[children].iter().map(|child| (child.stats, child.proven)), config.clamped_draw_value());
+                if agree || self.root().proven != Proven::Unknown {
+                    break;
+                }
+                self.propagate(rollouts, config, rng);
+            }
+        }
+
+        let children = self.children(root);
+        return selection.choose(self.nodes[children.clone()].iter().map(|child| (child.stats, child.proven)), config.clamped_draw_value(), rng).map(|offset| children.start + offset);
+    }
+
+    /// Moves the root down to the child reached by playing `move_index`, keeping the statistics of its subtree and dropping the rest of the tree.
+    /// Returns false if the move is not available, in which case the tree is left unchanged.
+    pub fn advance(&mut self, move_index: T::Move) -> bool {
+        let root = Tree::<T>::ROOT;
+        if ! self.nodes[root].expanded {
+            let moves = Tree::child_moves(&self.game);
+            self.expand(root, moves);
+        }
+
+        let child = self.children(root).find(|&child| self.nodes[child].move_index == move_index);
+        if let Some(child) = child {
+            self.game.update(move_index);
+            self.retain(child);
+            return true;
+        }
+
+        return false;
+    }
+
+    /// copies the subtree of `id` into a new arena with `id` as the root, breadth first so that siblings stay next to each other
+    fn retain(&mut self, id: NodeId) {
+        let mut nodes = vec![self.nodes[id].clone()];
+
+        let mut next = 0;
+        while next < nodes.len() {
+            let children = nodes[next].children();
+            nodes[next].first_child = nodes.len() as u32;
+            nodes.extend_from_slice(&self.nodes[children]);
+            next += 1;
+        }
+
+        self.nodes = nodes;
+    }
+
+    fn can_stop<S: SelectionPolicy, R: RolloutPolicy, E>(&self, config: &SearchConfig<S, R, E>, remaining_visits: usize) -> bool {
+        let children = self.children(Tree::<T>::ROOT);
+        return config.early_stop.can_stop(self.nodes[children].iter().map(|child| (child.stats, child.proven)), remaining_visits);
+    }
+
+    /// Runs `iters` iterations of the search, stopping early once the value of the root is proven or `config.early_stop` allows it.
+    /// Returns the number of iterations performed
+    pub fn predict<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, G: Rng + ?Sized>(&mut self, iters: usize, rollouts: usize, config: &SearchConfig<S, R, E>, rng: &mut G) -> usize {
+        for iter in 0..iters {
+            if self.root().proven != Proven::Unknown || self.can_stop(config, (iters - iter) * rollouts) {
+                return iter;
+            }
+            self.propagate(rollouts, config, rng);
+        }
+
+        return iters;
+    }
+
+    /// Runs the search until `budget_ms` milliseconds measured by `clock` have elapsed or the value of the root is proven,
+    /// returns the number of iterations performed. Of `config.early_stop` only the proven rule applies.
+    pub fn predict_for<S: SelectionPolicy, R: RolloutPolicy, E: Evaluator<T>, C: Clock, G: Rng + ?Sized>(&mut self, budget_ms: f64, rollouts: usize, config: &SearchConfig<S, R, E>, clock: &mut C, rng: &mut G) -> usize {
+        let start = clock.now_ms();
+        let mut iters = 0usize;
+
+        while self.root().proven == Proven::Unknown && ! self.can_stop(config, usize::MAX) && clock.now_ms() - start < budget_ms {
+            self.propagate(rollouts, config, rng);
+            iters += 1;
+        }
+
+        return iters;
+    }
+}
+
+
+#[cfg(test)]
+use super::tictactoe::{Square, TicTacToe};
+#[cfg(test)]
+use super::connect4::Connect4;
+#[cfg(test)]
+use super::clock::FakeClock;
+#[cfg(test)]
+use super::mcts::{EarlyStop, Node};
+#[cfg(test)]
+use super::rollout::UniformRollout;
+#[cfg(test)]
+use super::selection::{Rave, Ucb1};
+#[cfg(test)]
+use rand::{SeedableRng, rngs::StdRng};
+
+#[test]
+fn test_tree_propagate(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut tree = Tree::new(TicTacToe::from_string("...\n...\n...").unwrap());
+
+    assert_eq!(tree.predict(200, 1, &config, &mut rng), 200);
+    let root = tree.root();
+    assert_eq!(root.stats.visits, 200);
+    assert_eq!(root.stats.visits, root.stats.wins + root.stats.losses + root.stats.draws);
+    assert_eq!(tree.children(Tree::<TicTacToe>::ROOT).len(), 9);
+
+    // the first iteration only expands the root
+    let children = tree.children(Tree::<TicTacToe>::ROOT);
+    assert_eq!(children.clone().map(|child| tree.node(child).stats.visits).sum::<usize>(), 199);
+    for child in children {
+        let stats = tree.node(child).stats;
+        assert_eq!(stats.visits, stats.wins + stats.losses + stats.draws);
+        assert_eq!(tree.node(child).player, -1);
+    }
+}
+
+#[test]
+fn test_tree_matches_node(){
+    // with the same random numbers the arena and the recursive tree make the same decisions
+    for amaf in [false, true] {
+        let game = Connect4::<6,7,4>::empty();
+        let mut tree = Tree::new(game.clone());
+        let mut node = Node::new(game, 0);
+
+        if amaf {
+            let config = SearchConfig::<Rave, UniformRollout>::default();
+            tree.predict(500, 1, &config, &mut StdRng::seed_from_u64(3));
+            node.predict(500, 1, &config, &mut StdRng::seed_from_u64(3));
+        }
+        else {
+            let config = SearchConfig::<Ucb1, UniformRollout> { draw_value: -0.5, ..Default::default() };
+            tree.predict(500, 1, &config, &mut StdRng::seed_from_u64(3));
+            node.predict(500, 1, &config, &mut StdRng::seed_from_u64(3));
+        }
+
+        assert_eq!(tree.root().stats, node.stats());
+        for (id, child) in tree.children(Tree::<Connect4<6,7,4>>::ROOT).zip(node.children.iter()) {
+            assert_eq!(tree.node(id).move_index, child.move_index);
+            assert_eq!(tree.node(id).stats, child.stats());
+        }
+    }
+}
+
+#[test]
+fn test_tree_no_selectable_child(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut tree = Tree::new(TicTacToe::from_string("...\n...\n...").unwrap());
+    tree.predict(1, 1, &config, &mut rng);
+
+    let root = Tree::<TicTacToe>::ROOT;
+    for child in tree.children(root) {
+        tree.nodes[child].proven = Proven::Loss;
+    }
+    assert_eq!(tree.propagate(1, &config, &mut rng), (0, 0, 1));
+    let stats = tree.root().stats;
+    assert_eq!(stats.visits, stats.wins + stats.losses + stats.draws);
+    assert_eq!(tree.root().proven, Proven::Win);
+}
+
+#[test]
+fn test_tree_contempt(){
+    // O to move, c1 and b2 both hold the draw
+    let game = TicTacToe::from_string("O..\nX..\n..X").unwrap();
+    let choose = |draw_value: f32| {
+        let config = SearchConfig::<Ucb1, UniformRollout> { draw_value, ..Default::default() };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut tree = Tree::new(game.clone());
+        tree.predict(3000, 1, &config, &mut rng);
+        let child = tree.choose_move(&MoveSelection::MaxVisits, 1, &config, &mut rng).unwrap();
+        tree.node(child).move_index
+    };
+
+    assert_eq!(choose(0.), Square(0,2));
+    // with contempt O avoids the draw, and expects X to go for it
+    assert_eq!(choose(-0.8), Square(1,1));
+}
+
+#[test]
+fn test_tree_proven(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    // X wins by playing the top right corner
+    let mut tree = Tree::new(TicTacToe::from_string("XX.\nOO.\n...").unwrap());
+    let iters = tree.predict(1000, 1, &config, &mut rng);
+    assert!(iters < 1000);
+    assert_eq!(tree.root().proven, Proven::Loss);
+    let best = tree.get_most_visited_child(Tree::<TicTacToe>::ROOT).unwrap();
+    assert_eq!(tree.node(best).move_index, Square(0,2));
+    assert_eq!(tree.node(best).proven, Proven::Win);
+    assert_eq!(tree.predict(1000, 1, &config, &mut rng), 0);
+
+    // the only remaining move draws
+    let mut tree = Tree::new(TicTacToe::from_string("XOX\nXOO\nOX.").unwrap());
+    assert_eq!(tree.predict(100, 1, &config, &mut rng), 2);
+    assert_eq!(tree.root().proven, Proven::Draw);
+}
+
+#[test]
+fn test_tree_predict_early_stop(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout> { early_stop: EarlyStop { visit_gap: true, proven: true }, ..Default::default() };
+
+    // O has to block the last column
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ......X\n\
+                        O.....X\n\
+                        O.....X\n\
+                    ";
+    let mut tree = Tree::new(Connect4::<6,7,4>::from_string(str).unwrap());
+    assert!(tree.predict(2000, 1, &config, &mut rng) < 2000);
+    let best = tree.get_most_visited_child(Tree::<Connect4<6,7,4>>::ROOT).unwrap();
+    assert_eq!(tree.node(best).move_index, 6);
+
+    // only the proven rule applies to a search with a time budget
+    let mut tree = Tree::new(Connect4::<6,7,4>::from_string(str).unwrap());
+    let iters = tree.predict_for(1000., 1, &config, &mut FakeClock { time: 0., step: 1. }, &mut rng);
+    assert!(iters < 999);
+    assert_eq!(tree.root().proven, Proven::Unknown);
+}
+
+#[test]
+fn test_tree_choose_move(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let root = Tree::<Connect4<6,7,4>>::ROOT;
+
+    let mut tree = Tree::new(Connect4::<6,7,4>::empty());
+    assert_eq!(tree.choose_move(&MoveSelection::MaxVisits, 1, &config, &mut rng), None);
+    tree.predict(1000, 1, &config, &mut rng);
+    assert_eq!(tree.choose_move(&MoveSelection::MaxVisits, 1, &config, &mut rng), tree.get_most_visited_child(root));
+
+    // the search is extended until the most visited move has the highest value
+    let visits = tree.root().stats.visits;
+    let best = tree.choose_move(&MoveSelection::RobustMax { max_extension: 100000 }, 1, &config, &mut rng).unwrap();
+    let max_value = tree.children(root).map(|child| tree.node(child).value(0.)).fold(f32::NEG_INFINITY, f32::max);
+    assert_eq!(tree.node(best).value(0.), max_value);
+    assert_eq!(Some(best), tree.get_most_visited_child(root));
+    assert!(tree.root().stats.visits < visits + 100000);
+
+    // no extension without a budget
+    let visits = tree.root().stats.visits;
+    tree.choose_move(&MoveSelection::RobustMax { max_extension: 0 }, 1, &config, &mut rng).unwrap();
+    assert_eq!(tree.root().stats.visits, visits);
+}
+
+#[test]
+fn test_tree_predict_for(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut tree = Tree::new(TicTacToe::from_string("...\n...\n...").unwrap());
+
+    let mut clock = FakeClock { time: 0., step: 1. };
+    assert_eq!(tree.predict_for(10., 1, &SearchConfig::<Ucb1, UniformRollout>::default(), &mut clock, &mut rng), 9);
+    assert_eq!(tree.root().stats.visits, 9);
+}
+
+#[test]
+fn test_tree_advance(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut tree = Tree::new(TicTacToe::from_string("...\n...\n...").unwrap());
+    tree.predict(300, 1, &config, &mut rng);
+
+    let root = Tree::<TicTacToe>::ROOT;
+    let child = tree.children(root).find(|&child| tree.node(child).move_index == Square(1,1)).unwrap();
+    let stats = tree.node(child).stats;
+    let grandchildren: Vec<TreeNode<Square>> = tree.children(child).map(|id| tree.node(id).clone()).collect();
+    let node_count = tree.node_count();
+
+    assert!(tree.advance(Square(1,1)));
+    assert_eq!(tree.root().stats, stats);
+    assert_eq!(tree.root().player, -1);
+    assert_eq!(tree.game(), &TicTacToe::from_string("...\n.X.\n...").unwrap());
+    // the siblings and their subtrees are dropped
+    assert!(tree.node_count() < node_count);
+    for (id, grandchild) in tree.children(root).zip(grandchildren.iter()) {
+        assert_eq!(tree.node(id).stats, grandchild.stats);
+        assert_eq!(tree.node(id).move_index, grandchild.move_index);
+    }
+
+    assert!(! tree.advance(Square(1,1)));
+    tree.predict(100, 1, &config, &mut rng);
+    assert_eq!(tree.root().stats.visits, stats.visits + 100);
+
+    // children which were not expanded yet are created on demand
+    let mut tree = Tree::new(TicTacToe::from_string("...\n...\n...").unwrap());
+    assert!(tree.advance(Square(0,0)));
+    assert!(tree.advance(Square(2,2)));
+    assert_eq!(tree.root().player, 1);
+    assert_eq!(tree.node_count(), 1);
+    assert_eq!(tree.game(), &TicTacToe::from_string("X..\n...\n..O").unwrap());
+}
+
+#[test]
+fn test_tree_node_limit(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    let mut unlimited = Tree::new(Connect4::<6,7,4>::empty());
+    unlimited.predict(2000, 1, &config, &mut rng);
+
+    for node_limit in [NodeLimit::StopExpansion(300), NodeLimit::Prune(300)] {
+        let mut tree = Tree::new(Connect4::<6,7,4>::empty());
+        tree.set_node_limit(Some(node_limit));
+        assert_eq!(tree.predict(2000, 1, &config, &mut rng), 2000);
+
+        assert!(tree.node_count() <= 300, "{:?}", node_limit);
+        assert_eq!(tree.root().stats.visits, 2000);
+        let stats = tree.root().stats;
+        assert_eq!(stats.visits, stats.wins + stats.losses + stats.draws);
+        // the memory never grows beyond the limit
+        assert!(tree.memory_usage() < unlimited.memory_usage());
+        assert!(tree.memory_usage() <= std::mem::size_of::<Tree<Connect4<6,7,4>>>() + 300 * std::mem::size_of::<TreeNode<usize>>() + 1000);
+    }
+
+    // the best move is still found with a small tree
+    let str = "\
+                        .......\n\
+                        .......\n\
+                        .......\n\
+                        ......X\n\
+                        O.....X\n\
+                        O.....X\n\
+                    ";
+    let mut tree = Tree::new(Connect4::<6,7,4>::from_string(str).unwrap());
+    tree.set_node_limit(Some(NodeLimit::Prune(50)));
+    tree.predict(2000, 1, &config, &mut rng);
+    assert!(tree.node_count() <= 50);
+    let best = tree.get_most_visited_child(Tree::<Connect4<6,7,4>>::ROOT).unwrap();
+    assert_eq!(tree.node(best).move_index, 6);
+}
+
+#[test]
+fn test_tree_prune(){
+    let mut rng = StdRng::seed_from_u64(0);
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+    let mut tree = Tree::new(TicTacToe::from_string("...\n...\n...").unwrap());
+    tree.predict(1000, 1, &config, &mut rng);
+
+    let root = Tree::<TicTacToe>::ROOT;
+    let best = tree.get_most_visited_child(root).unwrap();
+    let best_stats = tree.node(best).stats;
+    let node_count = tree.node_count();
+
+    // lowering the limit prunes right away and keeps the statistics of the root and its children
+    tree.set_node_limit(Some(NodeLimit::Prune(node_count / 2)));
+    assert!(tree.node_count() <= node_count / 4);
+    assert_eq!(tree.children(root).len(), 9);
+    assert_eq!(tree.root().stats.visits, 1000);
+    let best = tree.get_most_visited_child(root).unwrap();
+    assert_eq!(tree.node(best).stats, best_stats);
+    // the most visited subtree is the last to be dropped
+    assert!(! tree.children(best).is_empty());
+
+    // pruned nodes are expanded again
+    tree.set_node_limit(None);
+    tree.predict(1000, 1, &config, &mut rng);
+    assert!(tree.node_count() > node_count / 4);
+}
+
+#[test]
+#[ignore]
+fn test_tree_speed(){
+    // run with `cargo test --release -- --ignored --nocapture test_tree_speed`
+    const ITERS: usize = 200000;
+    let config = SearchConfig::<Ucb1, UniformRollout>::default();
+
+    // the rollouts alone bound the speed of both trees
+    let start = std::time::Instant::now();
+    let mut game = Connect4::<6,7,4>::empty();
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..ITERS {
+        simulate(&mut game, 1, &config, &mut rng, None, None);
+    }
+    println!("Rollouts from the empty board: {:.0} rollouts/s", ITERS as f64 / start.elapsed().as_secs_f64());
+
+    let start = std::time::Instant::now();
+    let mut node = Node::new(Connect4::<6,7,4>::empty(), 0);
+    node.predict(ITERS, 1, &config, &mut StdRng::seed_from_u64(0));
+    drop(node);
+    let node_time = start.elapsed().as_secs_f64();
+
+    let start = std::time::Instant::now();
+    let mut tree = Tree::new(Connect4::<6,7,4>::empty());
+    tree.predict(ITERS, 1, &config, &mut StdRng::seed_from_u64(0));
+    let node_count = tree.node_count();
+    drop(tree);
+    let tree_time = start.elapsed().as_secs_f64();
+
+    println!("Node: {:.0} iterations/s, {} bytes per node", ITERS as f64 / node_time, std::mem::size_of::<Node<Connect4<6,7,4>>>());
+    println!("Tree: {:.0} iterations/s, {} bytes per node, {} nodes", ITERS as f64 / tree_time, std::mem::size_of::<TreeNode<usize>>(), node_count);
+    assert!(tree_time < node_time);
+}
@@ -32,9 +32,13 @@ export function get_next_best_move(board, player, iters) {
     return best_move;
 }
 
+// the winner, 0 for a draw and null while the game goes on
 export function check_score(board) {
     const board_str = get_board_string(board);
-    let score = get_score(board_str);
+    let outcome = get_score(board_str);
 
-    return score;
+    if (outcome == "x") return 1;
+    if (outcome == "o") return -1;
+    if (outcome == "draw") return 0;
+    return null;
 }
\ No newline at end of file
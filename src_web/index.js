@@ -140,12 +140,7 @@ function check_score_gui() {
     if (!active_game) return;
     let score = check_score(board);
 
-    if (score == HUMAN) { active_game = false; }
-    if (score == COMPUTER) { active_game = false; }
-
-    let draw = true;
-    for (let i = 0; i < 7; i++) draw &= board.top[i] == -1;
-    if (draw) { active_game = false; }
+    if (score !== null) { active_game = false; }
 
     if (!active_game) {
         let message = "";
@@ -157,7 +152,7 @@ function check_score_gui() {
             bot_score += 1;
             message = `${capitalize(current_bot)} won!`
         }
-        else if (draw) {
+        else if (score === 0) {
             bot_score += 0.5;
             human_score += 0.5;
             message = "Draw!"